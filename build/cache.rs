@@ -0,0 +1,534 @@
+use std::{
+	collections::HashMap,
+	fmt::Display,
+	fs::{self, File},
+	io::Read,
+	path::Path,
+	sync::{Arc, Mutex},
+};
+
+use dagrs::{Dag, DagError, DefaultTask, EnvVar, Input, Output, Task};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Deserializer, Serialize};
+use sha2::{
+	digest::{generic_array::GenericArray, OutputSizeUser},
+	Digest, Sha256,
+};
+
+pub const CACHE_FILE: &str = "target/build_cache.ron";
+// bump whenever the layout of the cache file or the hashes in it change
+const CACHE_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum NeedUpdate {
+	Yes,
+	No,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum CacheError {
+	#[error("IO error: {0}")]
+	IO(#[from] std::io::Error),
+	#[error("RON Error: {0}")]
+	Ron(#[from] ron::error::Error),
+	#[error("RON Spanned error: {0}")]
+	RonSpanned(#[from] ron::error::SpannedError),
+	#[error("Error: {0}")]
+	Other(String),
+}
+
+pub type CacheResult<T> = Result<T, CacheError>;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Sha256Hash([u8; 32]);
+
+impl From<GenericArray<u8, <Sha256 as OutputSizeUser>::OutputSize>> for Sha256Hash {
+	fn from(array: GenericArray<u8, <Sha256 as OutputSizeUser>::OutputSize>) -> Self {
+		let mut bytes = [0; 32];
+		bytes.copy_from_slice(&array);
+		Self(bytes)
+	}
+}
+
+impl<'de> Deserialize<'de> for Sha256Hash {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let string = <String>::deserialize(deserializer)?;
+		let mut bytes = [0; 32];
+		hex::decode_to_slice(string, &mut bytes).map_err(serde::de::Error::custom)?;
+		Ok(Self(bytes))
+	}
+}
+
+impl Serialize for Sha256Hash {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: serde::Serializer,
+	{
+		serde::Serialize::serialize(&hex::encode(self.0), serializer)
+	}
+}
+
+impl PartialEq<Option<Sha256Hash>> for Sha256Hash {
+	fn eq(&self, other: &Option<Self>) -> bool {
+		match other {
+			Some(other) => self == other,
+			None => false,
+		}
+	}
+}
+
+fn try_open(path: &Path) -> CacheResult<Option<File>> {
+	match File::open(path) {
+		Ok(file) => Ok(Some(file)),
+		Err(e) => {
+			if e.kind() == std::io::ErrorKind::NotFound {
+				Ok(None)
+			} else {
+				Err(e.into())
+			}
+		}
+	}
+}
+
+fn hash_file(file: &mut File) -> CacheResult<Sha256Hash> {
+	// probably worth buffering very large files
+	let mut file_contents = Vec::new();
+	file.read_to_end(&mut file_contents)?;
+	Ok(<Sha256 as Digest>::digest(file_contents).into())
+}
+
+fn hash_path(path: &Path) -> CacheResult<Option<Sha256Hash>> {
+	try_open(path)?
+		.map(|mut file| hash_file(&mut file))
+		.transpose()
+}
+
+enum Lookup {
+	Known(bool),
+	NeedsHash(Sha256Hash),
+}
+
+#[derive(Default, Debug)]
+pub struct Cache {
+	cached_map: HashMap<Box<Path>, Sha256Hash>,
+	current_map: HashMap<Box<Path>, Option<Sha256Hash>>,
+}
+
+#[cfg(test)]
+impl PartialEq for Cache {
+	fn eq(&self, other: &Self) -> bool {
+		self.cached_map == other.cached_map && self.current_map == other.current_map
+	}
+}
+
+impl Cache {
+	fn from_cached_map(cached_map: HashMap<Box<Path>, Sha256Hash>) -> Self {
+		Self {
+			cached_map,
+			current_map: HashMap::new(),
+		}
+	}
+
+	fn lookup(&mut self, path: &Path) -> Lookup {
+		if let Some(&cached) = self.cached_map.get(path) {
+			if let Some(&current) = self.current_map.get(path) {
+				Lookup::Known(cached != current)
+			} else {
+				Lookup::NeedsHash(cached)
+			}
+		} else {
+			self.current_map.insert(path.into(), None);
+			Lookup::Known(true)
+		}
+	}
+
+	#[cfg(test)]
+	pub fn is_modified(&mut self, path: &Path) -> CacheResult<bool> {
+		match self.lookup(path) {
+			Lookup::Known(modified) => Ok(modified),
+			Lookup::NeedsHash(cached) => {
+				let hash = hash_path(path)?;
+				self.current_map.insert(path.into(), hash);
+				Ok(cached != hash)
+			}
+		}
+	}
+}
+
+// hashing is the slow part, so only lock around the map accesses
+// to let the Modified tasks of independent files actually run in parallel
+pub fn is_modified_shared(cache: &Mutex<Cache>, path: &Path) -> CacheResult<bool> {
+	let cached = match cache.lock().unwrap().lookup(path) {
+		Lookup::Known(modified) => return Ok(modified),
+		Lookup::NeedsHash(cached) => cached,
+	};
+	let hash = hash_path(path)?;
+	cache.lock().unwrap().current_map.insert(path.into(), hash);
+	Ok(cached != hash)
+}
+
+fn any_need_update<E>(inputs: &Input) -> Result<NeedUpdate, E>
+where
+	E: From<CacheError>,
+{
+	for input in inputs.get_iter() {
+		if let Some(&updated) = input.get::<NeedUpdate>() {
+			if updated == NeedUpdate::Yes {
+				return Ok(NeedUpdate::Yes);
+			}
+		} else {
+			return Err(CacheError::Other("Input was not a boolean".to_string()).into());
+		}
+	}
+	Ok(NeedUpdate::No)
+}
+
+fn execute_if_needed<F, E>(inputs: &Input, env_var: Arc<EnvVar>, operation: &F) -> Output
+where
+	F: Fn(&Input, Arc<EnvVar>) -> Result<NeedUpdate, E>,
+	E: From<CacheError> + Display,
+{
+	match any_need_update(inputs).and_then(|update| {
+		if update == NeedUpdate::No {
+			Ok(NeedUpdate::No)
+		} else {
+			operation(inputs, env_var)
+		}
+	}) {
+		Ok(need_update) => Output::new(need_update),
+		Err(e) => Output::error(e.to_string()),
+	}
+}
+
+#[derive(Default)]
+pub struct TasksCache {
+	cache: Arc<Mutex<Cache>>,
+	exists_tasks: HashMap<Box<Path>, usize>,
+	modified_tasks: HashMap<Box<Path>, usize>,
+	tasks: Vec<DefaultTask>,
+}
+
+impl TasksCache {
+	fn from_cached_map(cached_map: HashMap<Box<Path>, Sha256Hash>) -> Self {
+		Self {
+			cache: Arc::new(Mutex::new(Cache::from_cached_map(cached_map))),
+			exists_tasks: HashMap::new(),
+			modified_tasks: HashMap::new(),
+			tasks: Vec::new(),
+		}
+	}
+
+	pub fn get_exists_task<T>(&mut self, path: T) -> usize
+	where
+		T: Into<Box<Path>>,
+	{
+		let path = path.into();
+		if let Some(&task_id) = self.exists_tasks.get(&path) {
+			task_id
+		} else {
+			let unmoved_path = path.clone();
+			let task = dagrs::DefaultTask::with_closure(
+				&format!("Exists|{}", path.display()),
+				move |_, _| {
+					Output::new({
+						match path.exists() {
+							true => NeedUpdate::No,
+							false => NeedUpdate::Yes,
+						}
+					})
+				},
+			);
+			let id = task.id();
+			self.tasks.push(task);
+			self.exists_tasks.insert(unmoved_path, id);
+			id
+		}
+	}
+
+	pub fn get_modified_task<T>(&mut self, path: T) -> usize
+	where
+		T: Into<Box<Path>>,
+	{
+		let path = path.into();
+		if let Some(&task_id) = self.modified_tasks.get(&path) {
+			task_id
+		} else {
+			let unmoved_path = path.clone();
+			let cache = self.cache.clone();
+			let task = dagrs::DefaultTask::with_closure(
+				&format!("Modified|{}", path.display()),
+				move |_, _| match is_modified_shared(&cache, &path) {
+					Ok(modified) => Output::new(match modified {
+						true => NeedUpdate::Yes,
+						false => NeedUpdate::No,
+					}),
+					Err(e) => Output::error(e.to_string()),
+				},
+			);
+			let id = task.id();
+			self.tasks.push(task);
+			self.modified_tasks.insert(unmoved_path, id);
+			id
+		}
+	}
+
+	pub fn make_task<F, I1, I2, E>(
+		&mut self,
+		name: &str,
+		inputs: I1,
+		outputs: I2,
+		closure: F,
+	) -> usize
+	where
+		F: Fn(&Input, Arc<EnvVar>) -> Result<NeedUpdate, E> + Send + Sync + 'static,
+		I1: IntoIterator<Item: AsRef<Path>>,
+		I2: IntoIterator<Item: AsRef<Path>>,
+		E: From<CacheError> + Display,
+	{
+		let mut task = DefaultTask::with_closure(name, move |inputs, env_var| {
+			execute_if_needed(&inputs, env_var, &closure)
+		});
+		task.set_predecessors_by_id(
+			inputs
+				.into_iter()
+				.map(|path| self.get_modified_task(path.as_ref())),
+		);
+		task.set_predecessors_by_id(
+			outputs
+				.into_iter()
+				.map(|path| self.get_exists_task(path.as_ref())),
+		);
+		let id = task.id();
+		self.tasks.push(task);
+		id
+	}
+
+	pub fn run_with_tasks<I>(&mut self, tasks: I) -> Result<bool, DagError>
+	where
+		I: IntoIterator<Item = DefaultTask>,
+	{
+		let tasks = self.tasks.drain(..).chain(tasks);
+		let mut dag = Dag::with_tasks(tasks.collect());
+		dag.start()
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+	version: u32,
+	hashes: HashMap<Box<Path>, Sha256Hash>,
+}
+
+#[derive(Deserialize)]
+struct CacheFileVersion {
+	version: u32,
+}
+
+pub fn load_cache_from(path: &Path) -> CacheResult<TasksCache> {
+	let mut contents = String::new();
+	match try_open(path)? {
+		Some(mut file) => file.read_to_string(&mut contents)?,
+		None => return Ok(TasksCache::default()),
+	};
+	// caches from before versioning don't parse as a CacheFileVersion at all
+	let version = ron::de::from_str::<CacheFileVersion>(&contents)
+		.map(|file| file.version)
+		.ok();
+	if version != Some(CACHE_VERSION) {
+		clear_cache(path)?;
+		return Ok(TasksCache::default());
+	}
+	let cache_file: CacheFile = ron::de::from_str(&contents)?;
+	Ok(TasksCache::from_cached_map(cache_file.hashes))
+}
+
+pub fn load_cache() -> CacheResult<TasksCache> {
+	load_cache_from(CACHE_FILE.as_ref())
+}
+
+pub fn save_cache_to(tasks_cache: &TasksCache, path: &Path) -> CacheResult<()> {
+	let writer = File::create(path)?;
+	let cache = tasks_cache.cache.lock().unwrap();
+	let mut merged_map = cache.cached_map.clone();
+	{
+		for (path, hash) in cache.current_map.iter() {
+			if let Some(hash) = hash {
+				merged_map.insert(path.clone(), *hash);
+			} else if let Ok(mut file) = File::open(path) {
+				if let Ok(hash) = hash_file(&mut file) {
+					merged_map.insert(path.clone(), hash);
+				}
+			}
+		}
+	}
+	ron::ser::to_writer_pretty(
+		writer,
+		&CacheFile {
+			version: CACHE_VERSION,
+			hashes: merged_map,
+		},
+		PrettyConfig::default()
+			.indentor("\t".to_string())
+			.new_line("\n".to_string()),
+	)?;
+	Ok(())
+}
+
+pub fn save_cache(tasks_cache: &TasksCache) -> CacheResult<()> {
+	save_cache_to(tasks_cache, CACHE_FILE.as_ref())
+}
+
+pub fn clear_cache(path: &Path) -> CacheResult<bool> {
+	fs::remove_file(path).map(|_| true).or_else(|e| {
+		if e.kind() == std::io::ErrorKind::NotFound {
+			Ok(false)
+		} else {
+			Err(e.into())
+		}
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use std::path::PathBuf;
+
+	use super::*;
+	use crate::temp_dir::TempDir;
+
+	fn temp_cache_path(name: &str) -> PathBuf {
+		std::env::temp_dir().join(format!("msu_launcher_{}.ron", name))
+	}
+
+	#[test]
+	fn test_serialization() {
+		let mut cache = Cache::default();
+		cache
+			.cached_map
+			.insert(Path::new("test").into(), Sha256Hash([0; 32]));
+		let serialized = ron::ser::to_string(&cache.cached_map).unwrap();
+		let new_cache = Cache::from_cached_map(ron::de::from_str(&serialized).unwrap());
+		assert_eq!(cache, new_cache);
+	}
+
+	#[test]
+	fn test_save_load() {
+		let cache_path = temp_cache_path("test_save_load");
+		let tasks_cache = TasksCache::default();
+		tasks_cache
+			.cache
+			.lock()
+			.unwrap()
+			.cached_map
+			.insert(Path::new("test").into(), Sha256Hash([0; 32]));
+		save_cache_to(&tasks_cache, &cache_path).unwrap();
+		let new_tasks_cache = load_cache_from(&cache_path).unwrap();
+		assert_eq!(
+			*tasks_cache.cache.lock().unwrap(),
+			*new_tasks_cache.cache.lock().unwrap()
+		);
+
+		tasks_cache
+			.cache
+			.lock()
+			.unwrap()
+			.current_map
+			.insert(Path::new("test").into(), Some(Sha256Hash([1; 32])));
+		save_cache_to(&tasks_cache, &cache_path).unwrap();
+		let new_tasks_cache = load_cache_from(&cache_path).unwrap();
+
+		let new_expected = Cache::from_cached_map(HashMap::from([(
+			Path::new("test").into(),
+			Sha256Hash([1; 32]),
+		)]));
+		assert_eq!(new_expected, *new_tasks_cache.cache.lock().unwrap());
+		fs::remove_file(cache_path).unwrap();
+	}
+
+	#[test]
+	fn test_version_mismatch_clears_cache() {
+		let cache_path = temp_cache_path("test_version_mismatch");
+		let old_map = HashMap::from([(Path::new("test").into(), Sha256Hash([0; 32]))]);
+		fs::write(&cache_path, ron::ser::to_string(&old_map).unwrap()).unwrap();
+		let tasks_cache = load_cache_from(&cache_path).unwrap();
+		assert_eq!(Cache::default(), *tasks_cache.cache.lock().unwrap());
+		assert!(!cache_path.exists());
+
+		let outdated = CacheFile {
+			version: CACHE_VERSION + 1,
+			hashes: old_map,
+		};
+		fs::write(&cache_path, ron::ser::to_string(&outdated).unwrap()).unwrap();
+		let tasks_cache = load_cache_from(&cache_path).unwrap();
+		assert_eq!(Cache::default(), *tasks_cache.cache.lock().unwrap());
+		assert!(!cache_path.exists());
+	}
+
+	#[test]
+	fn test_is_modified() {
+		let file_path = temp_cache_path("test_is_modified_input");
+		fs::write(&file_path, "contents").unwrap();
+		let hash = hash_file(&mut File::open(&file_path).unwrap()).unwrap();
+
+		let mut cache =
+			Cache::from_cached_map(HashMap::from([(file_path.clone().into_boxed_path(), hash)]));
+		assert!(!cache.is_modified(&file_path).unwrap());
+
+		let mut cache = Cache::from_cached_map(HashMap::from([(
+			file_path.clone().into_boxed_path(),
+			Sha256Hash([0; 32]),
+		)]));
+		assert!(cache.is_modified(&file_path).unwrap());
+		assert!(Cache::default().is_modified(&file_path).unwrap());
+		fs::remove_file(file_path).unwrap();
+	}
+
+	// the files are hashed on several threads sharing one cache, which has to end up the same as a serial pass
+	#[test]
+	fn parallel_hashing_matches_serial() {
+		const FILE_COUNT: usize = 8;
+		const FILE_SIZE: usize = 1024;
+
+		let dir = TempDir::new("parallel_hashing");
+		let paths: Vec<PathBuf> = (0..FILE_COUNT)
+			.map(|i| {
+				let path = dir.join(format!("{}.bin", i));
+				fs::write(&path, vec![i as u8; FILE_SIZE]).unwrap();
+				path
+			})
+			.collect();
+		// every other file is unchanged so both results get exercised
+		let cached_map: HashMap<Box<Path>, Sha256Hash> = paths
+			.iter()
+			.enumerate()
+			.map(|(i, path)| {
+				let hash = match i % 2 {
+					0 => hash_path(path).unwrap().unwrap(),
+					_ => Sha256Hash([0; 32]),
+				};
+				(path.clone().into_boxed_path(), hash)
+			})
+			.collect();
+
+		let mut serial_cache = Cache::from_cached_map(cached_map.clone());
+		let serial: Vec<bool> = paths
+			.iter()
+			.map(|path| serial_cache.is_modified(path).unwrap())
+			.collect();
+
+		let shared_cache = Mutex::new(Cache::from_cached_map(cached_map));
+		let parallel: Vec<bool> = std::thread::scope(|scope| {
+			let handles: Vec<_> = paths
+				.iter()
+				.map(|path| scope.spawn(|| is_modified_shared(&shared_cache, path).unwrap()))
+				.collect();
+			handles.into_iter().map(|h| h.join().unwrap()).collect()
+		});
+
+		assert_eq!(serial, parallel);
+		assert!(parallel.iter().enumerate().all(|(i, &m)| m == (i % 2 == 1)));
+		assert_eq!(serial_cache, shared_cache.into_inner().unwrap());
+	}
+}
@@ -0,0 +1,188 @@
+use std::path::Path;
+
+use anyhow::Result;
+use dioxus::prelude::*;
+
+use crate::{button::Button, config::Config, i18n::tr, modal, patcher_laa, steamless, update};
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+const REPO_URL: &str = "https://github.com/MSUTeam/MSU-Launcher";
+const KOFI_URL: &str = "https://ko-fi.com/enduriel";
+
+fn build_info() -> String {
+	let profile = if cfg!(debug_assertions) {
+		"debug"
+	} else {
+		"release"
+	};
+	let bundle = if cfg!(feature = "bundle") {
+		", bundled"
+	} else {
+		""
+	};
+	format!("{} build{}", profile, bundle)
+}
+
+async fn get_edition_description(config: ReadOnlySignal<Config, SyncStorage>) -> String {
+	let exe_path = match config.read().get_bb_exe_path() {
+		Some(exe_path) => exe_path,
+		None => return tr("about_game_not_found").to_string(),
+	};
+	// hashing the exe takes a moment, so keep it off the UI thread
+	let edition =
+		tokio::task::spawn_blocking(move || patcher_laa::detect_edition(exe_path.as_ref())).await;
+	match edition {
+		Ok(Ok(Some(edition))) => edition.to_string(),
+		Ok(Ok(None)) => tr("about_edition_unknown").to_string(),
+		Ok(Err(e)) => format!("{}: {}", tr("about_edition_error"), e),
+		Err(e) => format!("{}: {}", tr("about_edition_error"), e),
+	}
+}
+
+async fn get_exe_hash(config: ReadOnlySignal<Config, SyncStorage>) -> String {
+	let exe_path = match config.read().get_bb_exe_path() {
+		Some(exe_path) => exe_path,
+		None => return tr("about_game_not_found").to_string(),
+	};
+	match tokio::task::spawn_blocking(move || patcher_laa::exe_hash_hex(exe_path.as_ref())).await {
+		Ok(Ok(hash)) => hash,
+		Ok(Err(e)) => format!("{}: {}", tr("about_hash_error"), e),
+		Err(e) => format!("{}: {}", tr("about_hash_error"), e),
+	}
+}
+
+fn describe<T: std::fmt::Display>(result: Result<T>) -> String {
+	match result {
+		Ok(value) => value.to_string(),
+		Err(e) => format!("error: {}", e),
+	}
+}
+
+fn describe_path<P: AsRef<Path>>(path: Option<P>) -> String {
+	path.map_or_else(
+		|| "not found".to_string(),
+		|path| path.as_ref().display().to_string(),
+	)
+}
+
+// everything a bug report usually needs, meant to be pasted into an issue as is
+fn gather_diagnostics(config: &Config) -> String {
+	let exe_path = config.get_bb_exe_path();
+	let (edition, laa) = match &exe_path {
+		Some(exe_path) => (
+			describe(
+				patcher_laa::detect_edition(exe_path.as_ref()).map(|edition| {
+					edition.map_or_else(|| "unknown".to_string(), |edition| edition.to_string())
+				}),
+			),
+			describe(patcher_laa::is_laa(exe_path.as_ref())),
+		),
+		None => ("n/a".to_string(), "n/a".to_string()),
+	};
+	[
+		format!("MSU Launcher: v{} ({})", VERSION, build_info()),
+		format!("Game path: {}", describe_path(config.get_bb_path())),
+		format!("Exe path: {}", describe_path(exe_path.as_ref())),
+		format!("Data path: {}", describe_path(config.get_bb_data_path())),
+		format!("Mods path: {}", describe_path(config.get_mods_path())),
+		format!("Edition: {}", edition),
+		format!(
+			"Launches: {} (last {})",
+			config.get_launch_count(),
+			config
+				.get_last_launched()
+				.map_or_else(|| "never".to_string(), |time| time.to_rfc3339())
+		),
+		format!("4GB patched: {}", laa),
+		format!(
+			"Exe SHA256: {}",
+			exe_path.as_ref().map_or_else(
+				|| "n/a".to_string(),
+				|exe_path| describe(patcher_laa::exe_hash_hex(exe_path.as_ref()))
+			)
+		),
+		format!(
+			"Steamless {}: {} at {:?}",
+			steamless::STEAMLESS_VERSION,
+			describe(
+				steamless::verify_steamless_install(config.get_steamless_path())
+					.map(|_| "installed")
+			),
+			config.get_steamless_path()
+		),
+	]
+	.join("\n")
+}
+
+fn copy_diagnostics(config: ReadOnlySignal<Config, SyncStorage>) {
+	let config = config.read().clone();
+	spawn(async move {
+		match tokio::task::spawn_blocking(move || gather_diagnostics(&config)).await {
+			Ok(text) => {
+				let text = serde_json::to_string(&text).unwrap_or_default();
+				let _ = eval(&format!("navigator.clipboard.writeText({});", text));
+				tracing::info!("Copied diagnostics to the clipboard");
+			}
+			Err(e) => tracing::error!("Couldn't gather diagnostics: {}", e),
+		}
+	});
+}
+
+#[component]
+fn AboutRow(label: &'static str, children: Element) -> Element {
+	rsx!(
+		div { class: "flex justify-between space-x-8",
+			span { class: "text-gray-300", "{label}" }
+			span { {children} }
+		}
+	)
+}
+
+#[component]
+fn AboutPanel(
+	config: ReadOnlySignal<Config, SyncStorage>,
+	on_close: EventHandler<MouseEvent>,
+) -> Element {
+	modal::use_modal();
+	let edition = use_resource(move || get_edition_description(config));
+	let exe_hash = use_resource(move || get_exe_hash(config));
+	rsx!(
+		div { class: "fixed inset-0 z-10 flex justify-center items-center bg-black/70",
+			div { class: "flex flex-col space-y-2 rounded-lg bg-gray-800 p-6 normal-font text-lg",
+				h2 { class: "title-font text-3xl text-center", "MSU Launcher" }
+				AboutRow { label: tr("about_launcher_version"), "v{VERSION} ({build_info()})" }
+				AboutRow { label: tr("about_steamless_version"), "{steamless::STEAMLESS_VERSION}" }
+				AboutRow { label: tr("about_game_edition"),
+					{edition.read().clone().unwrap_or_else(|| tr("about_detecting").to_string())}
+				}
+				AboutRow { label: tr("about_exe_hash"),
+					span { class: "font-mono text-xs select-all",
+						{exe_hash.read().clone().unwrap_or_else(|| tr("about_detecting").to_string())}
+					}
+				}
+				div { class: "flex justify-center space-x-4 pt-2 underline",
+					a { href: REPO_URL, "GitHub" }
+					a { href: update::RELEASE_URL, "NexusMods" }
+					a { href: KOFI_URL, "Ko-fi" }
+				}
+				Button { class: "p-1", onclick: move |_| copy_diagnostics(config), {tr("about_copy_diagnostics")} }
+				Button { class: "p-1 text-xl", onclick: move |e| on_close.call(e), {tr("close")} }
+			}
+		}
+	)
+}
+
+#[component]
+pub fn AboutButton(
+	class: Option<String>,
+	style: Option<String>,
+	config: ReadOnlySignal<Config, SyncStorage>,
+) -> Element {
+	let mut open = use_signal(|| false);
+	rsx!(
+		Button { class, style, onclick: move |_| open.set(true), {tr("about")} }
+		if *open.read() {
+			AboutPanel { config, on_close: move |_| open.set(false) }
+		}
+	)
+}
@@ -1,7 +1,13 @@
 use dioxus::prelude::*;
+use msu_launcher::sq::{Readable, SaveGame};
+use std::io::Cursor;
 use std::path::PathBuf;
+use std::time::Duration;
 
-use crate::{patcher_laa, patcher_preload, steamless, Config};
+use crate::patcher_laa::Architecture;
+use crate::{
+	i18n::tr, log, modal, network, patcher_laa, patcher_preload, process, steamless, Config,
+};
 
 #[component]
 pub fn Button(
@@ -20,6 +26,12 @@ pub fn Button(
 			id,
 			disabled,
 			onclick: move |e| onclick.call(e),
+			// a focused button already handles Enter itself, so don't let it trigger the launch shortcut too
+			onkeydown: move |e: KeyboardEvent| {
+				if e.key() == Key::Enter {
+					e.stop_propagation();
+				}
+			},
 			{children}
 		}
 	)
@@ -62,14 +74,80 @@ pub fn ConfigButton(
 	)
 }
 
-async fn launch_game(config: ReadOnlySignal<Config, SyncStorage>) {
-	patcher_preload::async_gather_and_create_mod(config).await;
-	match config.read().launch_game() {
-		Ok(_) => tracing::info!("Launched Battle Brothers"),
+// Steam may need to start up or log in first, so this is generous
+const STEAM_LAUNCH_TIMEOUT: Duration = Duration::from_secs(60);
+
+// Steam accepting the steam:// url doesn't mean the game actually starts
+async fn fall_back_if_not_started(config: SyncSignal<Config>) {
+	if process::wait_for_process("BattleBrothers.exe", STEAM_LAUNCH_TIMEOUT).await {
+		return;
+	}
+	tracing::warn!("Steam didn't start Battle Brothers, launching BattleBrothers.exe directly");
+	let launched = config.read().launch_game_from_exe();
+	match launched {
+		Ok(Some(child)) => process::watch_game(child).await,
+		Ok(None) => {}
+		Err(e) => tracing::error!("Couldn't launch Battle Brothers: {}", e),
+	}
+}
+
+async fn launch_game(mut config: SyncSignal<Config>) {
+	if let Err(e) = patcher_laa::check_exe_updated(config) {
+		tracing::error!("{}", e);
+		return;
+	}
+	if config.read().auto_preload_on_launch() {
+		patcher_preload::async_gather_and_create_mod(config.into()).await;
+	}
+	let launched = config.with_mut(|c| c.launch_game());
+	match launched {
+		Ok(child) => {
+			// the game process is only known when it was launched directly
+			tracing::info!(
+				"Launched Battle Brothers {}",
+				if child.is_some() {
+					"directly"
+				} else {
+					"through Steam"
+				}
+			);
+			match child {
+				Some(child) => process::watch_game(child).await,
+				None => fall_back_if_not_started(config).await,
+			}
+		}
 		Err(e) => tracing::error!("Couldn't launch Battle Brothers: {}", e),
 	};
 }
 
+pub fn spawn_launch_game(config: SyncSignal<Config>) {
+	spawn(async move {
+		let _ = tokio::spawn(async move {
+			launch_game(config).await;
+		})
+		.await;
+	});
+}
+
+pub fn spawn_preload_patcher(config: ReadOnlySignal<Config, SyncStorage>) {
+	spawn(async move { patcher_preload::mt_gather_and_create_mod(config).await });
+}
+
+pub fn spawn_4gb_patcher(mut config: SyncSignal<Config>) {
+	spawn(async move {
+		let steamless_installed = config.with_mut(|c| c.check_steamless_installed());
+		if steamless_installed {
+			let _ = patcher_laa::patch_from_config(config);
+		} else if network::refresh_online_status(config).await {
+			let _ = steamless::mt_download_steamless_from_config(config).await;
+		} else {
+			tracing::error!(
+				"Can't download Steamless while offline, check your connection and try again"
+			);
+		}
+	});
+}
+
 #[component]
 pub fn DonateButton(
 	#[props(default="".to_string())] class: String,
@@ -84,7 +162,7 @@ pub fn DonateButton(
 					src: "assets/gfx/icons/kofi.svg"
 				}
 				div { class: "absolute top-1 w-[50%] h-[100%] right-3",
-					div { class: "text-[12px] text-gray-300", "Support me on" }
+					div { class: "text-[12px] text-gray-300", {tr("donate_support")} }
 					div { class: "text-3xl font-bold text-white", "Ko-fi" }
 				}
 			}
@@ -96,22 +174,49 @@ pub fn DonateButton(
 pub fn LaunchButton(
 	class: Option<String>,
 	style: Option<String>,
-	config: ReadOnlySignal<Config, SyncStorage>,
+	config: SyncSignal<Config>,
 ) -> Element {
 	rsx!(
 		Button {
 			class,
 			style,
 			disabled: use_memo(move || !config.read().bb_path_known()),
-			onclick: move |_| {
-				spawn(async move {
-					let _ = tokio::spawn(async move {
-							launch_game(config).await;
-						})
-						.await;
-				});
+			onclick: move |e: MouseEvent| {
+				// shift-click also opens the log history to follow the patcher and launch
+				if e.modifiers().contains(Modifiers::SHIFT) {
+					log::show_history();
+				}
+				spawn_launch_game(config)
 			},
-			"Launch Battle Brothers"
+			{tr("launch_game")}
+		}
+	)
+}
+
+fn toggle_direct_launch(mut config: SyncSignal<Config>) {
+	let enabled = !config.read().prefer_direct_launch();
+	if let Err(e) = config.with_mut(|c| c.set_prefer_direct_launch(enabled)) {
+		tracing::error!("Couldn't save settings: {}", e);
+	}
+}
+
+// installs outside the Steam library are always launched directly, whatever this shows
+#[component]
+pub fn LaunchMethodToggle(
+	class: Option<String>,
+	style: Option<String>,
+	config: SyncSignal<Config>,
+) -> Element {
+	rsx!(
+		Button {
+			class,
+			style,
+			onclick: move |_| toggle_direct_launch(config),
+			if config.read().prefer_direct_launch() {
+				{tr("launch_method_direct")}
+			} else {
+				{tr("launch_method_steam")}
+			}
 		}
 	)
 }
@@ -122,15 +227,53 @@ pub fn RunPreloadPatcherButton(
 	style: Option<String>,
 	config: ReadOnlySignal<Config, SyncStorage>,
 ) -> Element {
+	let mut confirming = use_signal(|| false);
+	// a hand-made file in place of the generated mod would silently be replaced
+	let run = move |_: MouseEvent| {
+		let foreign = config.read().get_bb_data_path().is_some_and(|data_path| {
+			patcher_preload::existing_mod(&data_path) == patcher_preload::ExistingMod::Foreign
+		});
+		if foreign {
+			confirming.set(true);
+		} else {
+			spawn_preload_patcher(config);
+		}
+	};
 	rsx!(
 		Button {
 			class,
 			style,
 			disabled: use_memo(move || !config.read().bb_path_known()),
-			onclick: move |_| {
-				spawn(async move { patcher_preload::mt_gather_and_create_mod(config).await });
-			},
-			"Run Preload Patcher"
+			onclick: run,
+			{tr("run_preload_patcher")}
+		}
+		if *confirming.read() {
+			ReplaceForeignModDialog {
+				on_confirm: move |_| {
+					confirming.set(false);
+					spawn_preload_patcher(config);
+				},
+				on_cancel: move |_| confirming.set(false),
+			}
+		}
+	)
+}
+
+#[component]
+fn ReplaceForeignModDialog(
+	on_confirm: EventHandler<MouseEvent>,
+	on_cancel: EventHandler<MouseEvent>,
+) -> Element {
+	modal::use_modal();
+	rsx!(
+		div { class: "fixed inset-0 z-10 flex justify-center items-center bg-black/70",
+			div { class: "flex flex-col space-y-2 rounded-lg bg-gray-800 p-6 normal-font text-lg max-w-xl",
+				p { {tr("replace_foreign_mod_warning")} }
+				div { class: "flex space-x-2",
+					Button { class: "p-1 flex-grow", onclick: move |e| on_confirm.call(e), {tr("replace_foreign_mod")} }
+					Button { class: "p-1 flex-grow", onclick: move |e| on_cancel.call(e), {tr("cancel")} }
+				}
+			}
 		}
 	)
 }
@@ -141,29 +284,25 @@ pub fn Run4GBPatcherButton(
 	style: Option<String>,
 	config: SyncSignal<Config>,
 ) -> Element {
-	config.with_mut(|c| c.check_steamless_installed());
+	// whether Steamless is installed is checked when the config loads and again when the patcher runs,
+	// hashing its files on every render would block the UI
 	rsx!(
 		Button {
 			class,
 			style,
 			disabled: use_memo(move || !config.read().bb_path_known()),
-			onclick: move |_| {
-				spawn(async move {
-					let steamless_installed = config
-						.with_mut(|c| { c.check_steamless_installed() });
-					if steamless_installed {
-						let _ = patcher_laa::patch_from_config(config.into());
-					} else {
-						let _ = steamless::mt_download_steamless_from_config(config).await;
-					}
-				});
-			},
+			onclick: move |_| spawn_4gb_patcher(config),
 			{
 				use_memo(move || {
-					if config.read().is_steamless_installed() {
-						"Run 4GB Patcher"
+					let config = config.read();
+					if config.was_steam_running_warned() {
+						tr("patch_4gb_steam_running")
+					} else if config.is_steamless_installed() {
+						tr("run_4gb_patcher")
+					} else if !config.is_online() {
+						tr("patch_4gb_offline")
 					} else {
-						"Install Steamless by atom0s for 4GB Patcher"
+						tr("install_steamless")
 					}
 				})
 			}
@@ -171,21 +310,336 @@ pub fn Run4GBPatcherButton(
 	)
 }
 
-fn set_game_location_from_files(mut config: SyncSignal<Config>, e: Event<FormData>) {
-	if let Some(files) = &e.files() {
-		let files = files.files();
-		if let Some(file) = files.first() {
-			let exe_path = PathBuf::from(file);
-			config.with_mut(move |c| match c.set_path_from_exe(&exe_path) {
-				Ok(path) => tracing::info!("Set game location to {}", path.display()),
-				Err(e) => tracing::error!("Failed to set game location: {:?}", e),
-			});
+fn uninstall_steamless(mut config: SyncSignal<Config>) {
+	match config.with_mut(|c| c.uninstall_steamless()) {
+		Ok(_) => tracing::info!("Removed Steamless, run the 4GB Patcher to download a clean copy"),
+		Err(e) => tracing::error!("Couldn't remove Steamless: {}", e),
+	}
+}
+
+#[component]
+pub fn ReinstallSteamlessButton(
+	class: Option<String>,
+	style: Option<String>,
+	config: SyncSignal<Config>,
+) -> Element {
+	rsx!(
+		Button {
+			class,
+			style,
+			disabled: use_memo(move || !config.read().is_steamless_installed()),
+			onclick: move |_| uninstall_steamless(config),
+			{tr("reinstall_steamless")}
+		}
+	)
+}
+
+fn redetect_game(mut config: SyncSignal<Config>) {
+	config.with_mut(|c| match c.redetect_game() {
+		Ok(path) => tracing::info!("Found Battle Brothers at {}", path.display()),
+		Err(e) => tracing::error!("Couldn't re-detect Battle Brothers: {}", e),
+	});
+}
+
+#[component]
+pub fn RedetectGameButton(
+	class: Option<String>,
+	style: Option<String>,
+	config: SyncSignal<Config>,
+) -> Element {
+	rsx!(
+		Button { class, style, onclick: move |_| redetect_game(config), {tr("redetect_game")} }
+	)
+}
+
+#[component]
+pub fn Check4GBPatchButton(
+	class: Option<String>,
+	style: Option<String>,
+	config: ReadOnlySignal<Config, SyncStorage>,
+) -> Element {
+	rsx!(
+		Button {
+			class,
+			style,
+			disabled: use_memo(move || !config.read().bb_path_known()),
+			onclick: move |_| {
+				match patcher_laa::is_patched_from_config(config) {
+					Ok(status) => tracing::info!("{}", status),
+					Err(e) => tracing::error!("Couldn't check 4GB Patch status: {}", e),
+				}
+			},
+			{tr("check_4gb_patch")}
 		}
+	)
+}
+
+// recomputed when the exe or the last patched time changes, other config writes don't rehash the exe
+#[component]
+pub fn PatchStatusBadge(
+	class: Option<String>,
+	style: Option<String>,
+	config: ReadOnlySignal<Config, SyncStorage>,
+) -> Element {
+	let exe = use_memo(move || {
+		let config = config.read();
+		config.bb_path_known().then(|| {
+			let exe_path = config
+				.get_launch_exe_path()
+				.map(|exe_path| exe_path.as_ref().to_path_buf());
+			(exe_path, config.get_last_patched())
+		})
+	});
+	let status = use_resource(move || async move {
+		let (exe_path, _) = exe()?;
+		let status = match exe_path {
+			// hashing the exe takes a moment, so keep it off the UI thread
+			Some(exe_path) => {
+				tokio::task::spawn_blocking(move || patcher_laa::patch_status(&exe_path))
+					.await
+					.ok()
+			}
+			None => None,
+		};
+		Some(match status {
+			Some(Ok(status)) if status.architecture == Architecture::X64 => {
+				tr("patch_status_not_needed")
+			}
+			Some(Ok(status)) if status.is_laa => tr("patch_status_applied"),
+			Some(Ok(status)) if status.edition.is_some() => tr("patch_status_not_applied"),
+			_ => tr("patch_status_unknown_exe"),
+		})
+	});
+	let status = status.read().flatten();
+	rsx!({
+		status.map(|status| {
+			rsx! {
+				p { class, style, "{status}" }
+			}
+		})
+	})
+}
+
+async fn run_self_test(config: ReadOnlySignal<Config, SyncStorage>) {
+	let Some(exe_path) = config.read().get_bb_exe_path() else {
+		tracing::error!("Couldn't find BattleBrothers.exe");
+		return;
+	};
+	let _operation = log::Operation::start("Running 4GB Patcher self-test...");
+	match tokio::task::spawn_blocking(move || patcher_laa::self_test(exe_path.as_ref())).await {
+		Ok(Ok(_)) => tracing::info!("Self-test passed, the 4GB Patcher works on this exe"),
+		Ok(Err(e)) => tracing::error!("Self-test failed: {:#}", e),
+		Err(e) => tracing::error!("Self-test failed: {}", e),
 	}
 }
 
 #[component]
-pub fn SetGameLocationInput(config: SyncSignal<Config>, id: String) -> Element {
+pub fn SelfTestButton(
+	class: Option<String>,
+	style: Option<String>,
+	config: ReadOnlySignal<Config, SyncStorage>,
+) -> Element {
+	rsx!(
+		Button {
+			class,
+			style,
+			disabled: use_memo(move || !config.read().bb_path_known()),
+			onclick: move |_| {
+				spawn(run_self_test(config));
+			},
+			{tr("self_test")}
+		}
+	)
+}
+
+async fn patch_specific_exe(mut config: SyncSignal<Config>, exe_path: PathBuf) {
+	let needs_steamless = match patcher_laa::is_steam_exe(&exe_path) {
+		Ok(is_steam) => is_steam,
+		Err(e) => {
+			tracing::error!("Couldn't read {}: {}", exe_path.display(), e);
+			return;
+		}
+	};
+	if needs_steamless && !config.with_mut(|c| c.check_steamless_installed()) {
+		if !network::refresh_online_status(config).await {
+			tracing::error!(
+				"Can't download Steamless while offline, check your connection and try again"
+			);
+			return;
+		}
+		steamless::mt_download_steamless_from_config(config).await;
+		if !config.read().is_steamless_installed() {
+			return;
+		}
+	}
+	let _ = patcher_laa::patch_chosen_exe(config, &exe_path);
+}
+
+fn restore_original_exe(mut config: SyncSignal<Config>) {
+	let Some(exe_path) = config.read().get_launch_exe_path() else {
+		tracing::error!("Couldn't find BattleBrothers.exe");
+		return;
+	};
+	let backup_dir = config.read().get_backup_dir().map(|dir| dir.to_path_buf());
+	match patcher_laa::restore_backup(exe_path.as_ref(), backup_dir.as_deref()) {
+		Ok(backup_path) => {
+			tracing::info!("Restored the original exe from {}", backup_path.display());
+			if let Err(e) = config.with_mut(|c| c.clear_last_patched()) {
+				tracing::error!("Couldn't save config: {}", e);
+			}
+		}
+		Err(e) => tracing::error!("Couldn't restore the original exe: {:#}", e),
+	}
+}
+
+// undoes the 4GB patch, e.g. to check whether an issue happens without it
+#[component]
+pub fn RestoreExeButton(
+	class: Option<String>,
+	style: Option<String>,
+	config: SyncSignal<Config>,
+) -> Element {
+	rsx!(
+		Button {
+			class,
+			style,
+			disabled: use_memo(move || !config.read().bb_path_known()),
+			onclick: move |_| restore_original_exe(config),
+			{tr("restore_original_exe")}
+		}
+	)
+}
+
+// patches a copy or another install's exe, independent of the configured game path
+#[component]
+pub fn PatchSpecificExeButton(
+	class: Option<String>,
+	style: Option<String>,
+	config: SyncSignal<Config>,
+) -> Element {
+	let id = "hidden-patch-exe-input-id";
+	rsx!(
+		input {
+			id,
+			r#type: "file",
+			accept: ".exe",
+			multiple: "false",
+			hidden: true,
+			onchange: move |e| {
+				if let Some(exe_path) = get_first_file(&e) {
+					spawn(patch_specific_exe(config, exe_path));
+				}
+			}
+		}
+		Button {
+			class,
+			style,
+			onclick: move |_| {
+				eval(&format!("document.getElementById('{}').click();", id));
+			},
+			{tr("patch_specific_exe")}
+		}
+	)
+}
+
+pub fn get_first_file(e: &Event<FormData>) -> Option<PathBuf> {
+	e.files()
+		.and_then(|files| files.files().first().map(PathBuf::from))
+}
+
+async fn check_save_round_trip(save_path: PathBuf) {
+	let name = save_path.display().to_string();
+	let result = tokio::task::spawn_blocking(move || {
+		let bytes = std::fs::read(&save_path)?;
+		let save_game = SaveGame::from_reader(&mut Cursor::new(&bytes))?;
+		tracing::info!("{} contains {}", save_path.display(), save_game.describe());
+		SaveGame::check_round_trip(&bytes)
+	})
+	.await;
+	match result {
+		Ok(Ok(_)) => tracing::info!("{} survived a read and re-save unchanged", name),
+		Ok(Err(e)) => tracing::error!("Round trip of {} failed: {}", name, e),
+		Err(e) => tracing::error!("Round trip of {} failed: {}", name, e),
+	}
+}
+
+// a developer tool for catching parser bugs with real saves, nothing is written back to disk
+#[component]
+pub fn CheckSaveRoundTripButton(class: Option<String>, style: Option<String>) -> Element {
+	let id = "hidden-save-input-id";
+	rsx!(
+		input {
+			id,
+			r#type: "file",
+			accept: ".sav",
+			multiple: "false",
+			hidden: true,
+			onchange: move |e| {
+				if let Some(save_path) = get_first_file(&e) {
+					spawn(check_save_round_trip(save_path));
+				}
+			}
+		}
+		Button {
+			class,
+			style,
+			onclick: move |_| {
+				eval(&format!("document.getElementById('{}').click();", id));
+			},
+			{tr("check_save_round_trip")}
+		}
+	)
+}
+
+fn set_game_location_from_files(
+	mut config: SyncSignal<Config>,
+	mut exe_failed: Signal<bool>,
+	e: Event<FormData>,
+) {
+	if let Some(exe_path) = get_first_file(&e) {
+		config.with_mut(move |c| match c.set_path_from_exe(&exe_path) {
+			Ok(path) => {
+				exe_failed.set(false);
+				// one message, so the warning isn't replaced straight away by the info
+				if let Ok(Architecture::X64) = patcher_laa::detect_architecture(&exe_path) {
+					tracing::warn!(
+						"Set game location to {}, but this looks like a 64-bit build, the 4GB Patch isn't applicable",
+						path.display()
+					)
+				} else {
+					tracing::info!("Set game location to {}", path.display())
+				}
+			}
+			Err(e) => {
+				exe_failed.set(true);
+				tracing::error!("Failed to set game location: {:?}", e)
+			}
+		});
+	}
+}
+
+fn set_game_location_from_data_folder(
+	mut config: SyncSignal<Config>,
+	mut exe_failed: Signal<bool>,
+	e: Event<FormData>,
+) {
+	if let Some(data_path) = get_first_file(&e) {
+		config.with_mut(move |c| match c.set_path_from_data_folder(&data_path) {
+			Ok(path) => {
+				exe_failed.set(false);
+				tracing::info!("Set game location to {}", path.display())
+			}
+			Err(e) => tracing::error!("Failed to set game location from data folder: {:?}", e),
+		});
+	}
+}
+
+#[component]
+pub fn SetGameLocationInput(
+	config: SyncSignal<Config>,
+	exe_failed: Signal<bool>,
+	id: String,
+) -> Element {
 	rsx!(
 		input {
 			id,
@@ -193,8 +647,27 @@ pub fn SetGameLocationInput(config: SyncSignal<Config>, id: String) -> Element {
 			accept: ".exe",
 			multiple: "false",
 			hidden: true,
-			onchange: move |e| { set_game_location_from_files(config, e) },
-			"Set Game Location"
+			onchange: move |e| { set_game_location_from_files(config, exe_failed, e) },
+			{tr("set_game_location")}
+		}
+	)
+}
+
+#[component]
+pub fn SetDataFolderInput(
+	config: SyncSignal<Config>,
+	exe_failed: Signal<bool>,
+	id: String,
+) -> Element {
+	rsx!(
+		input {
+			id,
+			r#type: "file",
+			directory: true,
+			multiple: "false",
+			hidden: true,
+			onchange: move |e| { set_game_location_from_data_folder(config, exe_failed, e) },
+			{tr("select_data_folder")}
 		}
 	)
 }
@@ -207,15 +680,30 @@ pub fn SetGameLocationButton(
 ) -> Element {
 	// this hack is necessary to use the hidden input pattern
 	let id = "hidden-input-id";
+	let data_id = "hidden-data-input-id";
+	let exe_failed = use_signal(|| false);
 	rsx!(
-		SetGameLocationInput { config, id: id.to_string() }
-		Button {
-			class,
-			style,
-			onclick: move |_| {
-				eval(&format!("document.getElementById('{}').click();", id));
-			},
-			"Set Game Location"
+		SetGameLocationInput { config, exe_failed, id: id.to_string() }
+		SetDataFolderInput { config, exe_failed, id: data_id.to_string() }
+		div { class: "flex flex-col space-y-1",
+			Button {
+				class: class.clone(),
+				style: style.clone(),
+				onclick: move |_| {
+					eval(&format!("document.getElementById('{}').click();", id));
+				},
+				{tr("set_game_location")}
+			}
+			if *exe_failed.read() {
+				Button {
+					class,
+					style,
+					onclick: move |_| {
+						eval(&format!("document.getElementById('{}').click();", data_id));
+					},
+					{tr("select_data_folder_instead")}
+				}
+			}
 		}
 	)
 }
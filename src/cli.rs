@@ -0,0 +1,246 @@
+use std::{ffi::OsString, path::PathBuf, process::ExitCode};
+
+use anyhow::{anyhow, Context, Result};
+use msu_launcher::sq;
+
+use crate::{
+	config::{self, Config},
+	patcher_laa, patcher_preload, process, steamless,
+};
+
+const USAGE: &str =
+	"Usage: MSULauncher [--preload <game_path> | --patch <exe_path> | --self-test <exe_path> | --convert-saves <saves_dir> <out_dir>]";
+
+enum Command {
+	Preload(PathBuf),
+	Patch(PathBuf),
+	SelfTest(PathBuf),
+	ConvertSaves(PathBuf, PathBuf),
+}
+
+fn parse_args<I: Iterator<Item = OsString>>(mut args: I) -> Result<Option<Command>> {
+	let flag = match args.next() {
+		Some(flag) => flag,
+		None => return Ok(None),
+	};
+	let command = match flag.to_str() {
+		Some("--preload") => Command::Preload(
+			args.next()
+				.context("--preload requires the path to the game folder")?
+				.into(),
+		),
+		Some("--patch") => Command::Patch(
+			args.next()
+				.context("--patch requires the path to BattleBrothers.exe")?
+				.into(),
+		),
+		Some("--self-test") => Command::SelfTest(
+			args.next()
+				.context("--self-test requires the path to BattleBrothers.exe")?
+				.into(),
+		),
+		Some("--convert-saves") => Command::ConvertSaves(
+			args.next()
+				.context("--convert-saves requires the path to the saves folder")?
+				.into(),
+			args.next()
+				.context("--convert-saves requires the folder to write the json files to")?
+				.into(),
+		),
+		_ => return Err(anyhow!("Unknown argument {:?}", flag)),
+	};
+	if let Some(arg) = args.next() {
+		return Err(anyhow!("Unexpected argument {:?}", arg));
+	}
+	Ok(Some(command))
+}
+
+// uses the same settings as the GUI, e.g. excluded mods and the mods folder, only the game folder is replaced
+fn run_preload(config: &Config, game_path: PathBuf) -> Result<()> {
+	let mut config = config.clone();
+	config.override_bb_path(game_path);
+	let data_path = config
+		.get_bb_data_path()
+		.context("Couldn't find a valid data folder in the game folder")?;
+	let mods_path = config.get_mods_path().with_context(|| {
+		format!(
+			"Couldn't find mods in {:?}, check mods_dir_override in config.toml",
+			config.get_mods_dir_override()
+		)
+	})?;
+	let summary = patcher_preload::sync_gather_and_create_mod(
+		&mods_path,
+		config.get_staging_mods_path().as_ref(),
+		&data_path,
+		config.get_excluded_mods(),
+		config.verbose_patcher(),
+		None,
+	)?;
+	for skipped_mod in &summary.mods_skipped {
+		eprintln!("Skipped {}: {:#}", skipped_mod.name(), skipped_mod.error);
+	}
+	if summary.rewritten {
+		println!("Created preload mod in {}", data_path.as_ref().display());
+	} else {
+		println!(
+			"Preload mod in {} is already up to date",
+			data_path.as_ref().display()
+		);
+	}
+	println!("Changes: {}", summary.describe_changes());
+	if !summary.has_resources() {
+		eprintln!("Warning: No mods found to patch — is your data folder correct?");
+	}
+	println!(
+		"Scanned {} mods ({} skipped): {}",
+		summary.mods_scanned,
+		summary.mods_skipped.len(),
+		summary.describe_counts()
+	);
+	Ok(())
+}
+
+// mirrors the 4GB Patcher button, which installs Steamless first when it's missing
+fn run_patch(config: &Config, exe_path: PathBuf) -> Result<()> {
+	let mut config = config.clone();
+	let is_steam_exe = patcher_laa::is_steam_exe(&exe_path)?;
+	if is_steam_exe && !config.check_steamless_installed() {
+		println!("Installing Steamless by atom0s to remove the Steam DRM");
+		tokio::runtime::Builder::new_current_thread()
+			.enable_all()
+			.build()
+			.context("Couldn't start the async runtime")?
+			.block_on(steamless::download_steamless(
+				steamless::ZIP_URL,
+				config.get_steamless_path(),
+			))
+			.context("Failed to download steamless")?;
+	}
+	if is_steam_exe && process::is_steam_running() {
+		eprintln!("Warning: Steam is running and may undo the patch, consider closing it first");
+	}
+	let outcome = patcher_laa::patch_exe(
+		&exe_path,
+		config.get_backup_dir(),
+		config.make_backups(),
+		config.launch_unpacked_exe(),
+		config.get_steamless_path(),
+	)?;
+	println!("{}", outcome);
+	Ok(())
+}
+
+fn run_convert_saves(saves_dir: PathBuf, out_dir: PathBuf) -> Result<()> {
+	let summary = sq::convert_saves_in_dir(&saves_dir, &out_dir)?;
+	for (save, error) in &summary.failed {
+		eprintln!("Couldn't convert {}: {:#}", save.display(), error);
+	}
+	println!("{} into {}", summary, out_dir.display());
+	Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn attach_console() {
+	use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+	// the bundled build uses the windows subsystem, so it has no console to print to by default
+	let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+#[cfg(not(target_os = "windows"))]
+fn attach_console() {}
+
+// returns None when no command line arguments were passed and the GUI should be launched instead
+pub fn run(config: &Config) -> Option<ExitCode> {
+	let command = match parse_args(std::env::args_os().skip(1)) {
+		Ok(Some(command)) => command,
+		Ok(None) => return None,
+		Err(e) => {
+			attach_console();
+			eprintln!("{}\n{}", e, USAGE);
+			return Some(ExitCode::from(2));
+		}
+	};
+	attach_console();
+	// the GUI shows this in the InfoPanel instead
+	if let Some(warning) = config::take_config_reset_warning() {
+		tracing::error!("{}", warning);
+	}
+	let result = match command {
+		Command::Preload(game_path) => run_preload(config, game_path),
+		Command::Patch(exe_path) => run_patch(config, exe_path),
+		Command::SelfTest(exe_path) => {
+			patcher_laa::self_test(&exe_path).map(|_| println!("Self-test passed"))
+		}
+		Command::ConvertSaves(saves_dir, out_dir) => run_convert_saves(saves_dir, out_dir),
+	};
+	match result {
+		Ok(_) => Some(ExitCode::SUCCESS),
+		Err(e) => {
+			eprintln!("Error: {:#}", e);
+			Some(ExitCode::FAILURE)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse(args: &[&str]) -> Result<Option<Command>> {
+		parse_args(args.iter().map(OsString::from))
+	}
+
+	#[test]
+	fn parse_no_args() {
+		assert!(parse(&[]).unwrap().is_none());
+	}
+
+	#[test]
+	fn parse_preload() {
+		match parse(&["--preload", "C:/Games/Battle Brothers"]).unwrap() {
+			Some(Command::Preload(path)) => {
+				assert_eq!(path, PathBuf::from("C:/Games/Battle Brothers"))
+			}
+			_ => panic!("Expected a preload command"),
+		}
+	}
+
+	#[test]
+	fn parse_patch() {
+		match parse(&["--patch", "BattleBrothers.exe"]).unwrap() {
+			Some(Command::Patch(path)) => assert_eq!(path, PathBuf::from("BattleBrothers.exe")),
+			_ => panic!("Expected a patch command"),
+		}
+	}
+
+	#[test]
+	fn parse_self_test() {
+		match parse(&["--self-test", "BattleBrothers.exe"]).unwrap() {
+			Some(Command::SelfTest(path)) => {
+				assert_eq!(path, PathBuf::from("BattleBrothers.exe"))
+			}
+			_ => panic!("Expected a self-test command"),
+		}
+	}
+
+	#[test]
+	fn parse_convert_saves() {
+		match parse(&["--convert-saves", "saves", "json"]).unwrap() {
+			Some(Command::ConvertSaves(saves_dir, out_dir)) => {
+				assert_eq!(saves_dir, PathBuf::from("saves"));
+				assert_eq!(out_dir, PathBuf::from("json"));
+			}
+			_ => panic!("Expected a convert-saves command"),
+		}
+	}
+
+	#[test]
+	fn parse_invalid() {
+		assert!(parse(&["--preload"]).is_err());
+		assert!(parse(&["--patch"]).is_err());
+		assert!(parse(&["--self-test"]).is_err());
+		assert!(parse(&["--convert-saves", "saves"]).is_err());
+		assert!(parse(&["--unknown"]).is_err());
+		assert!(parse(&["--preload", "a", "b"]).is_err());
+	}
+}
@@ -1,13 +1,17 @@
 use std::{
 	path::{Path, PathBuf},
+	process::Child,
 	str::FromStr,
+	sync::Mutex,
+	time::Duration,
 };
 
 use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Local, Utc};
 use serde::{Deserialize, Serialize};
 use steamlocate::SteamDir;
 
-use crate::steamless;
+use crate::{patcher_laa, process, steamless};
 
 const STEAMLESS_PATH_DEFAULT: &str = "./steamless";
 const BB_GAME_ID: u32 = 365360;
@@ -18,12 +22,84 @@ pub struct Config {
 	bb_path: Option<PathBuf>,
 	steamless_installed: bool,
 	steamless_path: PathBuf,
+	minimize_to_tray: bool,
+	mods_dir_override: Option<PathBuf>,
+	staging_mods_dir: Option<PathBuf>,
+	backup_dir: Option<PathBuf>,
+	excluded_mods: Vec<String>,
+	lang: Option<String>,
+	auto_preload_on_launch: bool,
+	info_retention_secs: u64,
+	verbose_patcher: bool,
+	log_rotation: LogRotation,
+	json_logs: bool,
+	make_backups: bool,
+	prefer_direct_launch: bool,
+	launch_unpacked_exe: bool,
+	launch_count: u32,
+	last_launched: Option<DateTime<Utc>>,
+	last_patched: Option<DateTime<Utc>>,
+	online: bool,
+	steam_running_warned: bool,
+	exe_update_warned: bool,
 }
 
 #[derive(Deserialize, Serialize)]
 struct SerializedConfig {
 	bb_path: Option<PathBuf>,
 	steamless_path: PathBuf,
+	#[serde(default)]
+	minimize_to_tray: bool,
+	#[serde(default)]
+	mods_dir_override: Option<PathBuf>,
+	#[serde(default)]
+	staging_mods_dir: Option<PathBuf>,
+	#[serde(default)]
+	backup_dir: Option<PathBuf>,
+	#[serde(default)]
+	excluded_mods: Vec<String>,
+	#[serde(default)]
+	lang: Option<String>,
+	#[serde(default = "default_true")]
+	auto_preload_on_launch: bool,
+	#[serde(default = "default_info_retention_secs")]
+	info_retention_secs: u64,
+	#[serde(default)]
+	verbose_patcher: bool,
+	#[serde(default)]
+	log_rotation: LogRotation,
+	#[serde(default)]
+	json_logs: bool,
+	#[serde(default = "default_true")]
+	make_backups: bool,
+	#[serde(default)]
+	prefer_direct_launch: bool,
+	#[serde(default)]
+	launch_unpacked_exe: bool,
+	#[serde(default)]
+	launch_count: u32,
+	#[serde(default)]
+	last_launched: Option<DateTime<Utc>>,
+	#[serde(default)]
+	last_patched: Option<DateTime<Utc>>,
+}
+
+// how often a new file is started in the logs folder, only read at startup
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+	Hourly,
+	#[default]
+	Daily,
+	Never,
+}
+
+fn default_true() -> bool {
+	true
+}
+
+fn default_info_retention_secs() -> u64 {
+	30
 }
 
 impl From<SerializedConfig> for Config {
@@ -32,6 +108,26 @@ impl From<SerializedConfig> for Config {
 			bb_path: value.bb_path,
 			steamless_installed: false,
 			steamless_path: value.steamless_path,
+			minimize_to_tray: value.minimize_to_tray,
+			mods_dir_override: value.mods_dir_override,
+			staging_mods_dir: value.staging_mods_dir,
+			backup_dir: value.backup_dir,
+			excluded_mods: value.excluded_mods,
+			lang: value.lang,
+			auto_preload_on_launch: value.auto_preload_on_launch,
+			info_retention_secs: value.info_retention_secs,
+			verbose_patcher: value.verbose_patcher,
+			log_rotation: value.log_rotation,
+			json_logs: value.json_logs,
+			make_backups: value.make_backups,
+			prefer_direct_launch: value.prefer_direct_launch,
+			launch_unpacked_exe: value.launch_unpacked_exe,
+			launch_count: value.launch_count,
+			last_launched: value.last_launched,
+			last_patched: value.last_patched,
+			online: true,
+			steam_running_warned: false,
+			exe_update_warned: false,
 		}
 	}
 }
@@ -41,21 +137,93 @@ impl From<Config> for SerializedConfig {
 		Self {
 			bb_path: value.bb_path,
 			steamless_path: value.steamless_path,
+			minimize_to_tray: value.minimize_to_tray,
+			mods_dir_override: value.mods_dir_override,
+			staging_mods_dir: value.staging_mods_dir,
+			backup_dir: value.backup_dir,
+			excluded_mods: value.excluded_mods,
+			lang: value.lang,
+			auto_preload_on_launch: value.auto_preload_on_launch,
+			info_retention_secs: value.info_retention_secs,
+			verbose_patcher: value.verbose_patcher,
+			log_rotation: value.log_rotation,
+			json_logs: value.json_logs,
+			make_backups: value.make_backups,
+			prefer_direct_launch: value.prefer_direct_launch,
+			launch_unpacked_exe: value.launch_unpacked_exe,
+			launch_count: value.launch_count,
+			last_launched: value.last_launched,
+			last_patched: value.last_patched,
 		}
 	}
 }
 
 const CONFIG_FILE: &str = "config.toml";
+// the first load happens before the logger is set up, so a reset is only reported later
+static CONFIG_RESET_WARNING: Mutex<Option<String>> = Mutex::new(None);
+const SAVE_ATTEMPTS: u32 = 3;
+const SAVE_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+fn set_config_reset_warning(warning: String) {
+	if let Ok(mut reset_warning) = CONFIG_RESET_WARNING.lock() {
+		*reset_warning = Some(warning);
+	}
+}
+
+// Some once, if loading config.toml had to fall back to the default settings
+pub fn take_config_reset_warning() -> Option<String> {
+	CONFIG_RESET_WARNING.lock().ok()?.take()
+}
+
+// antivirus scans and sync clients briefly lock config.toml, which shouldn't lose a settings change
+fn write_with_retry(path: &str, contents: &str) -> std::io::Result<()> {
+	let mut attempt = 1;
+	loop {
+		match std::fs::write(path, contents) {
+			Err(e) if attempt < SAVE_ATTEMPTS && process::is_file_locked(&e) => {
+				tracing::debug!("{} is locked, retrying: {}", path, e);
+				std::thread::sleep(SAVE_RETRY_DELAY);
+				attempt += 1;
+			}
+			result => return result,
+		}
+	}
+}
 
 fn find_steam() -> Result<SteamDir> {
 	steamlocate::SteamDir::locate().context("steamlocate couldn't locate Steam")
 }
 
-fn find_bb() -> Result<PathBuf> {
-	let steam_dir = find_steam()?;
-	match steam_dir.find_app(BB_GAME_ID)? {
+fn launch_game_through_steam() -> Result<()> {
+	let steam_exe = find_steam()?.path().join("steam.exe");
+	if !steam_exe.is_file() {
+		return Err(anyhow!("Couldn't find {}", steam_exe.display()));
+	}
+	std::process::Command::new(steam_exe)
+		.arg(format!("steam://rungameid/{}", BB_GAME_ID))
+		.spawn()
+		.context("Couldn't Launch Battle Brothers via steam")?;
+	Ok(())
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum FindGameError {
+	#[error("Steam not found, install Steam or set the path manually")]
+	SteamNotFound(#[source] steamlocate::Error),
+	#[error("Battle Brothers isn't installed in this Steam library")]
+	GameNotInstalled,
+	#[error("Couldn't read the Steam library")]
+	LibraryError(#[source] steamlocate::Error),
+}
+
+pub fn find_bb() -> Result<PathBuf, FindGameError> {
+	let steam_dir = steamlocate::SteamDir::locate().map_err(FindGameError::SteamNotFound)?;
+	match steam_dir
+		.find_app(BB_GAME_ID)
+		.map_err(FindGameError::LibraryError)?
+	{
 		Some((app, lib)) => Ok(lib.resolve_app_dir(&app)),
-		None => Err(anyhow!("Couldn't locate Battle Brothers")),
+		None => Err(FindGameError::GameNotInstalled),
 	}
 }
 
@@ -93,39 +261,199 @@ impl AsRef<Path> for ExePath {
 	}
 }
 
+// either the real data folder or a folder that already contains some mods
+fn is_plausible_mods_dir(path: &Path) -> bool {
+	std::fs::read_dir(path)
+		.map(|entries| {
+			entries.flatten().any(|e| {
+				let path = e.path();
+				path.file_name().is_some_and(|name| name == "data_001.dat")
+					|| path
+						.extension()
+						.is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+			})
+		})
+		.unwrap_or(false)
+}
+
+// Windows paths are case-insensitive, so a mis-cased name still points at the right file there
+fn file_name_is(path: &Path, expected: &str) -> bool {
+	path.file_name()
+		.and_then(|name| name.to_str())
+		.is_some_and(|name| {
+			if cfg!(windows) {
+				name.eq_ignore_ascii_case(expected)
+			} else {
+				name == expected
+			}
+		})
+}
+
+pub const WRITE_CHECK_FILE: &str = ".write_check";
+
+// the read-only attribute means nothing for folders and ACLs deny writes without setting it,
+// so the only reliable check is actually writing something
+pub fn check_writable(path: &Path) -> std::io::Result<()> {
+	let probe = path.join(WRITE_CHECK_FILE);
+	std::fs::write(&probe, b"")?;
+	std::fs::remove_file(&probe)
+}
+
+// canonicalize gives \\?\C:\... on Windows, which would never compare equal to the path Steam reports
+fn without_verbatim_prefix(path: PathBuf) -> PathBuf {
+	match path.to_str().and_then(|path| path.strip_prefix(r"\\?\")) {
+		Some(stripped) if !stripped.starts_with(r"UNC\") => PathBuf::from(stripped),
+		_ => path,
+	}
+}
+
+// validated on the canonical path, so a symlink is judged by the file it actually points at
+fn bb_dir_from_exe(exe_path: &Path) -> Result<PathBuf> {
+	let exe_path = without_verbatim_prefix(
+		exe_path
+			.canonicalize()
+			.with_context(|| format!("Couldn't resolve {}", exe_path.display()))?,
+	);
+	if !file_name_is(&exe_path, "BattleBrothers.exe") {
+		return Err(anyhow!("Not a Battle Brothers exe"));
+	}
+	let win32_dir = exe_path.parent().context("Couldn't get win32 dir")?;
+	if !file_name_is(win32_dir, "win32") {
+		return Err(anyhow!("Not a Battle Brothers win32 dir"));
+	}
+	let bb_dir = win32_dir.parent().context("Couldn't get bb dir")?;
+	if !bb_dir.join("data").join("data_001.dat").exists() {
+		return Err(anyhow!("Couldn't find valid data folder"));
+	}
+	Ok(bb_dir.to_path_buf())
+}
+
 impl Default for Config {
 	fn default() -> Self {
 		Self {
 			bb_path: find_bb().ok(),
 			steamless_installed: false,
 			steamless_path: PathBuf::from_str(STEAMLESS_PATH_DEFAULT).unwrap(),
+			minimize_to_tray: false,
+			mods_dir_override: None,
+			staging_mods_dir: None,
+			backup_dir: None,
+			excluded_mods: Vec::new(),
+			lang: None,
+			auto_preload_on_launch: true,
+			info_retention_secs: default_info_retention_secs(),
+			verbose_patcher: false,
+			log_rotation: LogRotation::default(),
+			json_logs: false,
+			make_backups: true,
+			prefer_direct_launch: false,
+			launch_unpacked_exe: false,
+			launch_count: 0,
+			last_launched: None,
+			last_patched: None,
+			online: true,
+			steam_running_warned: false,
+			exe_update_warned: false,
 		}
 	}
 }
 
 impl Config {
+	// a missing config.toml is a fresh install, a broken one is moved aside so it isn't overwritten
 	pub fn load_or_default() -> Self {
-		match Self::load() {
-			Ok(config) => config,
-			Err(_) => Config::default(),
-		}
+		Self::load_or_default_from(Path::new(CONFIG_FILE))
 	}
 
-	#[cfg(test)]
-	pub fn from_path(path: PathBuf) -> Self {
-		Self {
-			bb_path: Some(path),
-			steamless_installed: false,
-			steamless_path: PathBuf::from_str(STEAMLESS_PATH_DEFAULT).unwrap(),
-		}
+	fn load_or_default_from(path: &Path) -> Self {
+		let config_text = match std::fs::read_to_string(path) {
+			Ok(config_text) => config_text,
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Config::default(),
+			Err(e) => {
+				set_config_reset_warning(format!(
+					"Couldn't read {} ({}), using the default settings for now",
+					path.display(),
+					e
+				));
+				return Config::default();
+			}
+		};
+		let error = match toml::from_str(&config_text) {
+			Ok(config) => return config,
+			Err(e) => e,
+		};
+		let backup_path = path.with_file_name(format!(
+			"config_corrupt_{}.toml",
+			Local::now().format("%Y%m%d_%H%M%S")
+		));
+		let backup = match std::fs::rename(path, &backup_path) {
+			Ok(_) => format!("the broken file was kept as {}", backup_path.display()),
+			Err(e) => format!("the broken file couldn't be backed up: {}", e),
+		};
+		set_config_reset_warning(format!(
+			"{} is corrupt and your settings were reset, {} ({})",
+			path.display(),
+			backup,
+			error.message()
+		));
+		Config::default()
+	}
+
+	// only for this run, e.g. a game folder passed on the command line, the saved location is kept
+	pub fn override_bb_path(&mut self, path: PathBuf) {
+		self.bb_path = Some(path);
 	}
 
 	pub fn save(&self) -> Result<()> {
 		let config_text = toml::to_string(self).context("Couldn't serialize config file")?;
-		std::fs::write(CONFIG_FILE, config_text).context("Couldn't write config file")?;
+		write_with_retry(CONFIG_FILE, &config_text).context("Couldn't write config file")?;
+		Ok(())
+	}
+
+	// for hand-editing, saved first so there's a file to open on a fresh install
+	pub fn open_config_file(&self) -> Result<PathBuf> {
+		if !Path::new(CONFIG_FILE).exists() {
+			self.save()?;
+		}
+		// absolute, since the editor's working directory isn't the launcher's
+		let path = std::fs::canonicalize(CONFIG_FILE).context("Couldn't find config file")?;
+		open::that(&path).with_context(|| format!("Couldn't open {}", path.display()))?;
+		Ok(path)
+	}
+
+	// for sharing a setup or moving it to another machine
+	pub fn export_to(&self, path: &Path) -> Result<()> {
+		let config_text = toml::to_string(self).context("Couldn't serialize config file")?;
+		std::fs::write(path, config_text)
+			.with_context(|| format!("Couldn't write config to {}", path.display()))?;
+		Ok(())
+	}
+
+	// the imported paths may not exist on this machine, callers should check get_bb_data_path afterwards
+	pub fn import_from(&mut self, path: &Path) -> Result<()> {
+		let config_text = std::fs::read_to_string(path)
+			.with_context(|| format!("Couldn't read {}", path.display()))?;
+		let config: Config = toml::from_str(&config_text)
+			.with_context(|| format!("Couldn't deserialize {}", path.display()))?;
+		self.replace_with(config);
+		self.save()
+	}
+
+	// picks up edits made to config.toml while the launcher is running
+	pub fn reload(&mut self) -> Result<()> {
+		let config = Self::load()?;
+		self.replace_with(config);
 		Ok(())
 	}
 
+	// state that only lives for this run isn't in the file, so it's carried over
+	fn replace_with(&mut self, mut config: Config) {
+		config.online = self.online;
+		config.steam_running_warned = self.steam_running_warned;
+		config.exe_update_warned = self.exe_update_warned;
+		config.check_steamless_installed();
+		*self = config;
+	}
+
 	fn load() -> Result<Self> {
 		let config_text =
 			std::fs::read_to_string(CONFIG_FILE).context("Couldn't read config file")?;
@@ -138,6 +466,10 @@ impl Config {
 		self.bb_path.is_some()
 	}
 
+	pub fn get_bb_path(&self) -> Option<&Path> {
+		self.bb_path.as_deref()
+	}
+
 	// todo check that exe exists
 	pub fn get_bb_exe_path(&self) -> Option<ExePath> {
 		self.bb_path
@@ -146,6 +478,23 @@ impl Config {
 			.filter(|exe_path| exe_path.as_ref().exists())
 	}
 
+	// only exists once the 4GB patcher has run with launch_unpacked_exe enabled
+	pub fn get_unpacked_exe_path(&self) -> Option<ExePath> {
+		self.get_bb_exe_path()
+			.map(|exe_path| ExePath::new(patcher_laa::unpacked_exe_path(exe_path.as_ref())))
+			.filter(|exe_path| exe_path.as_ref().exists())
+	}
+
+	// the exe that actually gets launched, and so the one whose patch status matters
+	pub fn get_launch_exe_path(&self) -> Option<ExePath> {
+		if self.launch_unpacked_exe {
+			if let Some(unpacked) = self.get_unpacked_exe_path() {
+				return Some(unpacked);
+			}
+		}
+		self.get_bb_exe_path()
+	}
+
 	pub fn get_bb_data_path(&self) -> Option<DataPath> {
 		self.bb_path
 			.as_ref()
@@ -153,22 +502,81 @@ impl Config {
 			.filter(|data_path| data_path.join("data_001.dat").exists())
 	}
 
-	pub fn set_path_from_exe<'a>(&'a mut self, exe_path: &'a Path) -> Result<&'a Path> {
-		if exe_path.file_name().context("Couldn't get exe file name")? != "BattleBrothers.exe" {
-			return Err(anyhow!("Not a Battle Brothers exe"));
+	// the folder the preload patcher scans for mods, the generated mod still goes in the data folder
+	pub fn get_mods_path(&self) -> Option<DataPath> {
+		match &self.mods_dir_override {
+			Some(path) => Some(DataPath::new(path.clone()))
+				.filter(|mods_path| is_plausible_mods_dir(mods_path.as_ref())),
+			None => self.get_bb_data_path(),
 		}
-		let win32_dir = exe_path.parent().context("Couldn't get win32 dir")?;
-		if win32_dir
-			.file_name()
-			.context("Couldn't get win32 dir name")?
-			!= "win32"
-		{
-			return Err(anyhow!("Not a Battle Brothers win32 dir"));
+	}
+
+	pub fn get_mods_dir_override(&self) -> Option<&Path> {
+		self.mods_dir_override.as_deref()
+	}
+
+	// mods that aren't installed yet, e.g. a modpack being put together, whose resources the preload patcher includes
+	pub fn get_staging_mods_path(&self) -> Option<DataPath> {
+		self.staging_mods_dir
+			.as_ref()
+			.map(|path| DataPath::new(path.clone()))
+			.filter(|staging_path| staging_path.as_ref().is_dir())
+	}
+
+	// None means backups are made next to the exe
+	pub fn get_backup_dir(&self) -> Option<&Path> {
+		self.backup_dir.as_deref()
+	}
+
+	// without backups a patched exe can only be restored by verifying the game files
+	pub fn make_backups(&self) -> bool {
+		self.make_backups
+	}
+
+	pub fn set_make_backups(&mut self, enabled: bool) -> Result<()> {
+		self.make_backups = enabled;
+		self.save()
+	}
+
+	// file names of mods the preload patcher shouldn't gather resources from
+	pub fn get_excluded_mods(&self) -> &[String] {
+		&self.excluded_mods
+	}
+
+	pub fn set_mod_excluded(&mut self, mod_name: &str, excluded: bool) -> Result<()> {
+		self.excluded_mods.retain(|name| name != mod_name);
+		if excluded {
+			self.excluded_mods.push(mod_name.to_string());
+		}
+		self.save()
+	}
+
+	pub fn set_path_from_exe(&mut self, exe_path: &Path) -> Result<&Path> {
+		self.bb_path = Some(bb_dir_from_exe(exe_path)?);
+		self.save()?;
+		self.bb_path.as_deref().context("Couldn't set game path")
+	}
+
+	// for when the game has been moved since its path was saved, only finds Steam installs
+	pub fn redetect_game(&mut self) -> Result<&Path> {
+		let bb_path = find_bb()?;
+		if !bb_path.join("data").join("data_001.dat").exists() {
+			return Err(anyhow!("Couldn't find valid data folder in {:?}", bb_path));
 		}
-		let bb_dir = win32_dir.parent().context("Couldn't get bb dir")?;
-		if !bb_dir.join("data").join("data_001.dat").exists() {
-			return Err(anyhow!("Couldn't find valid data folder"));
+		self.bb_path = Some(bb_path);
+		self.save()?;
+		self.bb_path.as_deref().context("Couldn't set game path")
+	}
+
+	// fallback for installs where the exe isn't in the usual win32 layout
+	pub fn set_path_from_data_folder<'a>(&'a mut self, data_path: &'a Path) -> Result<&'a Path> {
+		if !file_name_is(data_path, "data") {
+			return Err(anyhow!("Not a Battle Brothers data folder"));
 		}
+		if !data_path.join("data_001.dat").exists() {
+			return Err(anyhow!("Couldn't find data_001.dat in data folder"));
+		}
+		let bb_dir = data_path.parent().context("Couldn't get bb dir")?;
 		self.bb_path = Some(bb_dir.to_path_buf());
 		self.save()?;
 
@@ -180,6 +588,13 @@ impl Config {
 		self.steamless_installed
 	}
 
+	// the next 4GB patch downloads a clean copy
+	pub fn uninstall_steamless(&mut self) -> Result<()> {
+		steamless::remove_steamless_files(&self.steamless_path)?;
+		self.steamless_installed = false;
+		self.save()
+	}
+
 	pub fn is_steamless_installed(&self) -> bool {
 		self.steamless_installed
 	}
@@ -188,17 +603,140 @@ impl Config {
 		&self.steamless_path
 	}
 
-	fn launch_game_from_exe(&self) -> Result<()> {
+	// None uses English, see locales/en.toml for adding a translation
+	pub fn get_lang(&self) -> Option<&str> {
+		self.lang.as_deref()
+	}
+
+	pub fn auto_preload_on_launch(&self) -> bool {
+		self.auto_preload_on_launch
+	}
+
+	pub fn set_auto_preload_on_launch(&mut self, enabled: bool) -> Result<()> {
+		self.auto_preload_on_launch = enabled;
+		self.save()
+	}
+
+	// how long an info message stays in the info panel, None keeps it until the next one
+	pub fn get_info_retention(&self) -> Option<Duration> {
+		Some(Duration::from_secs(self.info_retention_secs)).filter(|retention| !retention.is_zero())
+	}
+
+	// also writes which mods each resource came from into the generated mod
+	pub fn verbose_patcher(&self) -> bool {
+		self.verbose_patcher
+	}
+
+	// skips Steam even for the Steam install, so the game runs without the overlay
+	pub fn prefer_direct_launch(&self) -> bool {
+		self.prefer_direct_launch
+	}
+
+	pub fn set_prefer_direct_launch(&mut self, enabled: bool) -> Result<()> {
+		self.prefer_direct_launch = enabled;
+		self.save()
+	}
+
+	// keeps the Steam exe as is and launches a separate patched copy, so Steam verifying the game can't undo the patch
+	pub fn launch_unpacked_exe(&self) -> bool {
+		self.launch_unpacked_exe
+	}
+
+	pub fn set_launch_unpacked_exe(&mut self, enabled: bool) -> Result<()> {
+		self.launch_unpacked_exe = enabled;
+		self.save()
+	}
+
+	pub fn get_log_rotation(&self) -> LogRotation {
+		self.log_rotation
+	}
+
+	// one JSON object per line in the log files, for parsing the logs attached to bug reports
+	pub fn json_logs(&self) -> bool {
+		self.json_logs
+	}
+
+	pub fn minimize_to_tray(&self) -> bool {
+		self.minimize_to_tray
+	}
+
+	pub fn set_online(&mut self, online: bool) {
+		self.online = online;
+	}
+
+	pub fn is_online(&self) -> bool {
+		self.online
+	}
+
+	// set once the user has been warned that Steam is running, the next patch attempt goes ahead anyway
+	pub fn set_steam_running_warned(&mut self, warned: bool) {
+		self.steam_running_warned = warned;
+	}
+
+	pub fn was_steam_running_warned(&self) -> bool {
+		self.steam_running_warned
+	}
+
+	pub fn get_last_patched(&self) -> Option<DateTime<Utc>> {
+		self.last_patched
+	}
+
+	pub fn set_last_patched(&mut self) -> Result<()> {
+		self.last_patched = Some(Utc::now());
+		self.save()
+	}
+
+	pub fn clear_last_patched(&mut self) -> Result<()> {
+		self.last_patched = None;
+		self.save()
+	}
+
+	pub fn set_exe_update_warned(&mut self, warned: bool) {
+		self.exe_update_warned = warned;
+	}
+
+	pub fn was_exe_update_warned(&self) -> bool {
+		self.exe_update_warned
+	}
+
+	// also the fallback for when Steam doesn't start the game
+	pub fn launch_game_from_exe(&self) -> Result<Option<Child>> {
 		let exe_path = self
-			.get_bb_exe_path()
+			.get_launch_exe_path()
 			.context("Couldn't find BattleBrothers.exe")?;
-		std::process::Command::new(exe_path.as_ref())
+		let child = std::process::Command::new(exe_path.as_ref())
 			.spawn()
 			.context("Couldn't launch Battle Brothers")?;
-		Ok(())
+		Ok(Some(child))
 	}
 
-	pub fn launch_game(&self) -> Result<()> {
+	pub fn get_launch_count(&self) -> u32 {
+		self.launch_count
+	}
+
+	pub fn get_last_launched(&self) -> Option<DateTime<Utc>> {
+		self.last_launched
+	}
+
+	// returns the game process when it was launched directly, launching through Steam doesn't give us one
+	pub fn launch_game(&mut self) -> Result<Option<Child>> {
+		let child = self.start_game()?;
+		self.launch_count += 1;
+		self.last_launched = Some(Utc::now());
+		// the game is already starting, a failed save shouldn't be reported as a failed launch
+		if let Err(e) = self.save() {
+			tracing::warn!("Couldn't save the launch count: {}", e);
+		}
+		Ok(child)
+	}
+
+	fn start_game(&self) -> Result<Option<Child>> {
+		// Steam would start the original exe rather than the unpacked one
+		if self.prefer_direct_launch
+			|| (self.launch_unpacked_exe && self.get_unpacked_exe_path().is_some())
+		{
+			return self.launch_game_from_exe();
+		}
 		let found_path = find_bb();
 		let bb_path = self.bb_path.as_ref();
 		match (found_path, bb_path) {
@@ -206,13 +744,16 @@ impl Config {
 				if &found_path != bb_path {
 					self.launch_game_from_exe()
 				} else {
-					let steam_dir = find_steam()?;
-					let steam_path = steam_dir.path();
-					std::process::Command::new(steam_path.join("steam.exe"))
-						.arg(format!("steam://rungameid/{}", BB_GAME_ID))
-						.spawn()
-						.context("Couldn't Launch Battle Brothers via steam")?;
-					Ok(())
+					match launch_game_through_steam() {
+						Ok(()) => Ok(None),
+						Err(e) => {
+							tracing::warn!(
+								"{:#}, launching BattleBrothers.exe directly instead",
+								e
+							);
+							self.launch_game_from_exe()
+						}
+					}
 				}
 			}
 			(_, Some(_)) => self.launch_game_from_exe(),
@@ -220,3 +761,72 @@ impl Config {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::temp_dir::TempDir;
+
+	fn write_game(dir: &Path) -> PathBuf {
+		std::fs::create_dir_all(dir.join("win32")).unwrap();
+		std::fs::create_dir_all(dir.join("data")).unwrap();
+		std::fs::write(dir.join("data").join("data_001.dat"), b"").unwrap();
+		let exe_path = dir.join("win32").join("BattleBrothers.exe");
+		std::fs::write(&exe_path, b"exe").unwrap();
+		exe_path
+	}
+
+	#[test]
+	fn corrupt_config_is_backed_up() {
+		let dir = TempDir::new("corrupt_config");
+		let path = dir.join(CONFIG_FILE);
+		Config::load_or_default_from(&path);
+		assert!(take_config_reset_warning().is_none());
+
+		std::fs::write(&path, "make_backups = [not toml").unwrap();
+		let config = Config::load_or_default_from(&path);
+		assert!(config.make_backups());
+		assert!(!path.exists());
+		let warning = take_config_reset_warning().unwrap();
+		assert!(warning.contains("settings were reset"), "{}", warning);
+		let backups: Vec<_> = std::fs::read_dir(&dir).unwrap().flatten().collect();
+		assert_eq!(backups.len(), 1);
+		assert_eq!(
+			std::fs::read_to_string(backups[0].path()).unwrap(),
+			"make_backups = [not toml"
+		);
+		assert!(take_config_reset_warning().is_none());
+	}
+
+	#[test]
+	fn bb_dir_is_found_from_exe() {
+		let dir = TempDir::new("bb_dir_from_exe");
+		let exe_path = write_game(&dir);
+		let canonical_dir = without_verbatim_prefix(dir.canonicalize().unwrap());
+		assert_eq!(bb_dir_from_exe(&exe_path).unwrap(), canonical_dir);
+
+		let mis_cased = dir.join("win32").join("battlebrothers.exe");
+		assert_eq!(bb_dir_from_exe(&mis_cased).is_ok(), cfg!(windows));
+
+		std::fs::write(dir.join("win32").join("Other.exe"), b"exe").unwrap();
+		assert!(bb_dir_from_exe(&dir.join("win32").join("Other.exe")).is_err());
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn symlinked_exe_resolves_to_real_game() {
+		let dir = TempDir::new("symlinked_exe");
+		let exe_path = write_game(&dir.join("game"));
+		// looks like a game install but has no data folder of its own
+		let link_path = dir.join("fake").join("win32").join("BattleBrothers.exe");
+		std::fs::create_dir_all(link_path.parent().unwrap()).unwrap();
+		std::os::unix::fs::symlink(&exe_path, &link_path).unwrap();
+		assert_eq!(
+			bb_dir_from_exe(&link_path).unwrap(),
+			dir.join("game").canonicalize().unwrap()
+		);
+
+		std::fs::remove_file(&exe_path).unwrap();
+		assert!(bb_dir_from_exe(&link_path).is_err());
+	}
+}
@@ -0,0 +1,164 @@
+use dioxus::prelude::*;
+
+use crate::{
+	button::Button,
+	config::Config,
+	i18n::tr,
+	log, modal,
+	patcher_preload::{self, GeneratedMod},
+};
+
+async fn load_generated_mod(
+	config: ReadOnlySignal<Config, SyncStorage>,
+) -> Result<(GeneratedMod, Option<String>), String> {
+	let data_path = config
+		.read()
+		.get_bb_data_path()
+		.ok_or_else(|| tr("generated_mod_no_data_folder").to_string())?;
+	match tokio::task::spawn_blocking(move || patcher_preload::read_generated_mod(&data_path)).await
+	{
+		Ok(Ok(generated_mod)) => Ok((generated_mod, None)),
+		Ok(Err(e)) => Err(format!("{:#}", e)),
+		Err(e) => Err(e.to_string()),
+	}
+}
+
+// runs the scan the patcher would, the summary replaces the patcher's success message
+async fn load_preview(
+	config: ReadOnlySignal<Config, SyncStorage>,
+) -> Result<(GeneratedMod, Option<String>), String> {
+	let data_path = config
+		.read()
+		.get_bb_data_path()
+		.ok_or_else(|| tr("generated_mod_no_data_folder").to_string())?;
+	let mods_path = config
+		.read()
+		.get_mods_path()
+		.ok_or_else(|| tr("generated_mod_no_mods_folder").to_string())?;
+	let staging_path = config.read().get_staging_mods_path();
+	let excluded_mods = config.read().get_excluded_mods().to_vec();
+	let verbose = config.read().verbose_patcher();
+	match tokio::task::spawn_blocking(move || {
+		let operation = log::Operation::start_cancellable("Previewing Preload Patcher...");
+		patcher_preload::preview_mod(
+			&mods_path,
+			staging_path.as_ref(),
+			&data_path,
+			&excluded_mods,
+			verbose,
+			Some(&operation),
+		)
+	})
+	.await
+	{
+		Ok(Ok((resources, summary))) => {
+			let description = format!(
+				"Scanned {} mods ({} skipped), {} ({})",
+				summary.mods_scanned,
+				summary.mods_skipped.len(),
+				summary.describe_counts(),
+				summary.describe_pending_changes()
+			);
+			Ok((GeneratedMod::from(&resources), Some(description)))
+		}
+		Ok(Err(e)) => Err(format!("{:#}", e)),
+		Err(e) => Err(e.to_string()),
+	}
+}
+
+#[component]
+fn GeneratedModSection(title: &'static str, contents: String) -> Element {
+	rsx!(
+		p { class: "text-gray-300", "{title}" }
+		pre { class: "rounded bg-gray-900 p-2 text-sm overflow-x-auto", "{contents}" }
+	)
+}
+
+#[component]
+fn GeneratedModPanel(
+	config: ReadOnlySignal<Config, SyncStorage>,
+	// shows what the patcher would write instead of the mod that's currently in the data folder
+	preview: bool,
+	on_close: EventHandler<MouseEvent>,
+) -> Element {
+	modal::use_modal();
+	let generated_mod = use_resource(move || async move {
+		if preview {
+			load_preview(config).await
+		} else {
+			load_generated_mod(config).await
+		}
+	});
+	let contents = match &*generated_mod.read() {
+		None => rsx!( p { {tr("generated_mod_loading")} } ),
+		Some(Err(e)) => rsx!( p { "{e}" } ),
+		Some(Ok((generated_mod, summary))) => rsx!(
+			{summary.as_ref().map(|summary| rsx!( p { class: "text-gray-300", "{summary}" } ))}
+			GeneratedModSection { title: tr("generated_mod_script"), contents: generated_mod.mod_string.clone() }
+			for (hook, hook_contents) in generated_mod.hooks.iter() {
+				GeneratedModSection { key: "{hook.name}", title: tr(hook.title_key), contents: hook_contents.clone() }
+			}
+			if !generated_mod.sources.is_empty() {
+				GeneratedModSection { title: tr("generated_mod_sources"), contents: generated_mod.sources.clone() }
+			}
+		),
+	};
+	// drawn over the settings panel it's opened from
+	rsx!(
+		div { class: "fixed inset-0 z-20 flex justify-center items-center bg-black/70",
+			div { class: "flex flex-col space-y-2 rounded-lg bg-gray-800 p-6 normal-font text-lg w-[90%] max-h-[90%]",
+				h2 { class: "title-font text-3xl text-center",
+					if preview {
+						{tr("preview_generated_mod")}
+					} else {
+						{tr("generated_mod")}
+					}
+				}
+				div { class: "flex flex-col space-y-2 overflow-y-auto", {contents} }
+				Button { class: "p-1 text-xl", onclick: move |e| on_close.call(e), {tr("close")} }
+			}
+		}
+	)
+}
+
+#[component]
+pub fn ShowGeneratedModButton(
+	class: Option<String>,
+	style: Option<String>,
+	config: ReadOnlySignal<Config, SyncStorage>,
+) -> Element {
+	let mut open = use_signal(|| false);
+	rsx!(
+		Button {
+			class,
+			style,
+			disabled: use_memo(move || !config.read().bb_path_known()),
+			onclick: move |_| open.set(true),
+			{tr("show_generated_mod")}
+		}
+		if *open.read() {
+			GeneratedModPanel { config, preview: false, on_close: move |_| open.set(false) }
+		}
+	)
+}
+
+#[component]
+pub fn PreviewPreloadButton(
+	class: Option<String>,
+	style: Option<String>,
+	config: ReadOnlySignal<Config, SyncStorage>,
+) -> Element {
+	let mut open = use_signal(|| false);
+	rsx!(
+		Button {
+			class,
+			style,
+			disabled: use_memo(move || !config.read().bb_path_known()),
+			onclick: move |_| open.set(true),
+			{tr("preview_preload")}
+		}
+		if *open.read() {
+			GeneratedModPanel { config, preview: true, on_close: move |_| open.set(false) }
+		}
+	)
+}
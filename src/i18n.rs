@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use once_cell::sync::{Lazy, OnceCell};
+
+const LOCALES_DIR: &str = "locales";
+const ENGLISH_STR: &str = include_str!("../locales/en.toml");
+
+type Strings = HashMap<String, String>;
+
+static ENGLISH: Lazy<Strings> =
+	Lazy::new(|| toml::from_str(ENGLISH_STR).expect("Couldn't parse locales/en.toml"));
+static SELECTED: OnceCell<Strings> = OnceCell::new();
+
+fn load_locale(lang: &str) -> Result<Strings> {
+	let path = std::path::Path::new(LOCALES_DIR).join(format!("{}.toml", lang));
+	let text =
+		std::fs::read_to_string(&path).with_context(|| format!("Couldn't read {:?}", path))?;
+	toml::from_str(&text).with_context(|| format!("Couldn't parse {:?}", path))
+}
+
+// only runs once at startup, so changing the language needs a restart
+pub fn init(lang: Option<&str>) {
+	let Some(lang) = lang.filter(|lang| *lang != "en") else {
+		return;
+	};
+	match load_locale(lang) {
+		Ok(strings) => {
+			let _ = SELECTED.set(strings);
+		}
+		Err(e) => tracing::warn!("Couldn't load language {}, using English: {:#}", lang, e),
+	}
+}
+
+// falls back to English, then to the key itself so a missing string is obvious rather than blank
+pub fn tr(key: &'static str) -> &'static str {
+	SELECTED
+		.get()
+		.and_then(|strings| strings.get(key))
+		.or_else(|| ENGLISH.get(key))
+		.map(String::as_str)
+		.unwrap_or(key)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn english_fallback() {
+		assert_eq!(tr("launch_game"), "Launch Battle Brothers");
+		assert_eq!(tr("not_a_real_key"), "not_a_real_key");
+	}
+}
@@ -0,0 +1,6 @@
+// the parts of the launcher that don't depend on the GUI, so other tools can read and write save files
+// the binary reaches them through `msu_launcher::` instead of declaring its own copy of the modules
+pub mod sq;
+// test-only, so each target compiles its own copy: main.rs and tests/build_cache.rs declare it too
+#[cfg(test)]
+mod temp_dir;
@@ -1,13 +1,24 @@
 use dioxus::prelude::*;
+use std::collections::VecDeque;
 use std::fmt::Write;
+use std::sync::{
+	atomic::{AtomicBool, Ordering},
+	Arc, Mutex,
+};
 use tokio::sync::broadcast;
 use tracing::level_filters::LevelFilter;
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_subscriber::{
+	fmt::format::FmtSpan,
 	layer::{Context, SubscriberExt},
 	Layer,
 };
 
+use crate::{
+	config::{Config, LogRotation},
+	i18n::tr,
+};
+
 static LOG_CHANNEL: once_cell::sync::Lazy<(
 	broadcast::Sender<LogUpdate>,
 	broadcast::Receiver<LogUpdate>,
@@ -55,14 +66,42 @@ where
 	}
 }
 
-pub(crate) static TRACING: once_cell::sync::Lazy<()> = once_cell::sync::Lazy::new(|| {
-	let file_appender = RollingFileAppender::new(Rotation::DAILY, "logs", "msu_launcher.log");
+fn to_rotation(log_rotation: LogRotation) -> Rotation {
+	match log_rotation {
+		LogRotation::Hourly => Rotation::HOURLY,
+		LogRotation::Daily => Rotation::DAILY,
+		LogRotation::Never => Rotation::NEVER,
+	}
+}
+
+// main loads config.toml before calling this, anything logged while loading it is dropped
+pub fn init(log_rotation: LogRotation, json_logs: bool) {
+	// separate files so switching formats never mixes them in one file
+	let file_name = if json_logs {
+		"msu_launcher.json.log"
+	} else {
+		"msu_launcher.log"
+	};
+	let file_appender = RollingFileAppender::new(to_rotation(log_rotation), "logs", file_name);
 	let (non_blocking, guard) = tracing_appender::non_blocking(file_appender);
-	let file_layer = FilteringLayer::new(
-		tracing_subscriber::fmt::layer()
-			.with_writer(non_blocking)
-			.with_ansi(false),
-	);
+	// only one of these is Some, the console and InfoPanel stay human readable either way
+	let file_layer = (!json_logs).then(|| {
+		FilteringLayer::new(
+			tracing_subscriber::fmt::layer()
+				.with_writer(non_blocking.clone())
+				.with_ansi(false)
+				// logs how long each span took when it closes, so the file shows where a slow or failed operation got to
+				.with_span_events(FmtSpan::CLOSE),
+		)
+	});
+	let json_file_layer = json_logs.then(|| {
+		FilteringLayer::new(
+			tracing_subscriber::fmt::layer()
+				.json()
+				.with_writer(non_blocking)
+				.with_span_events(FmtSpan::CLOSE),
+		)
+	});
 
 	let env_filter = tracing_subscriber::EnvFilter::builder()
 		.with_default_directive(LevelFilter::INFO.into())
@@ -76,23 +115,159 @@ pub(crate) static TRACING: once_cell::sync::Lazy<()> = once_cell::sync::Lazy::ne
 		.with(env_filter)
 		.with(console_layer)
 		.with(file_layer)
+		.with(json_file_layer)
 		.with(info_logger);
 
 	tracing::subscriber::set_global_default(subscriber).expect("Failed to set subscriber");
 
-	GUARD.with(|g| {
-		*g.borrow_mut() = Some(guard);
-	});
-});
+	if let Ok(mut g) = GUARD.lock() {
+		*g = Some(guard);
+	}
+}
+
+static GUARD: Mutex<Option<tracing_appender::non_blocking::WorkerGuard>> = Mutex::new(None);
 
-thread_local! {
-	static GUARD: std::cell::RefCell<Option<tracing_appender::non_blocking::WorkerGuard>> = const { std::cell::RefCell::new(None) };
+// dropping the guard waits for the file writer's thread to write what's still buffered
+// the desktop event loop ends the process without running destructors, so this is called before it does
+pub fn flush() {
+	if let Ok(mut guard) = GUARD.lock() {
+		guard.take();
+	}
 }
 
 #[derive(Clone)]
 enum LogUpdate {
 	Info(Box<str>),
 	Error(Box<str>),
+	Operation(Option<Box<str>>),
+	Progress(Option<f32>),
+	Cancellable(CancelToken),
+	CancellableEnded(CancelToken),
+	Notice(Option<Box<str>>),
+	ShowHistory,
+}
+
+// how many messages the expanded InfoPanel keeps, the full log is in the logs folder
+const HISTORY_LEN: usize = 100;
+
+// a line that stays until it's replaced or cleared, unlike info messages
+pub fn set_notice<S: Into<Box<str>>>(notice: Option<S>) {
+	let _ = LOG_CHANNEL
+		.0
+		.send(LogUpdate::Notice(notice.map(Into::into)));
+}
+
+// expands the InfoPanel into its scrollable history, e.g. to follow everything a launch logs
+pub fn show_history() {
+	let _ = LOG_CHANNEL.0.send(LogUpdate::ShowHistory);
+}
+
+// each cancellable operation has its own, several can run at once and the Cancel button
+// should only stop the one it's shown for
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+	fn cancel(&self) {
+		self.0.store(true, Ordering::Relaxed);
+	}
+
+	fn is_cancelled(&self) -> bool {
+		self.0.load(Ordering::Relaxed)
+	}
+
+	fn is_same(&self, other: &CancelToken) -> bool {
+		Arc::ptr_eq(&self.0, &other.0)
+	}
+}
+
+// returned by cancellable operations, so callers can tell a cancel apart from a failure
+#[derive(thiserror::Error, Debug)]
+#[error("Cancelled")]
+pub struct Cancelled;
+
+pub fn is_cancelled(error: &anyhow::Error) -> bool {
+	error.is::<Cancelled>()
+}
+
+// a sticky message shown for the duration of a long running operation,
+// separate from the transient info line which gets replaced by every event
+pub struct Operation {
+	// None for operations that can't be cancelled
+	cancel: Option<CancelToken>,
+}
+
+impl Operation {
+	pub fn start<S: Into<Box<str>>>(message: S) -> Self {
+		let _ = LOG_CHANNEL
+			.0
+			.send(LogUpdate::Operation(Some(message.into())));
+		Self { cancel: None }
+	}
+
+	// also shows a Cancel button, the operation has to poll check_cancelled to stop
+	pub fn start_cancellable<S: Into<Box<str>>>(message: S) -> Self {
+		let mut operation = Self::start(message);
+		let cancel = CancelToken::default();
+		let _ = LOG_CHANNEL.0.send(LogUpdate::Cancellable(cancel.clone()));
+		operation.cancel = Some(cancel);
+		operation
+	}
+
+	pub fn check_cancelled(&self) -> anyhow::Result<()> {
+		if self.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+			Err(Cancelled.into())
+		} else {
+			Ok(())
+		}
+	}
+
+	// what the Cancel button does, without going through the InfoPanel
+	#[cfg(test)]
+	pub fn cancel(&self) {
+		if let Some(cancel) = &self.cancel {
+			cancel.cancel();
+		}
+	}
+
+	pub fn update<S: Into<Box<str>>>(&self, message: S) {
+		let _ = LOG_CHANNEL
+			.0
+			.send(LogUpdate::Operation(Some(message.into())));
+	}
+
+	// fraction from 0.0 to 1.0, the bar stays hidden for operations that never report any
+	pub fn set_progress(&self, fraction: f32) {
+		let _ = LOG_CHANNEL
+			.0
+			.send(LogUpdate::Progress(Some(fraction.clamp(0.0, 1.0))));
+	}
+}
+
+impl Drop for Operation {
+	fn drop(&mut self) {
+		if let Some(cancel) = self.cancel.take() {
+			let _ = LOG_CHANNEL.0.send(LogUpdate::CancellableEnded(cancel));
+		}
+		let _ = LOG_CHANNEL.0.send(LogUpdate::Progress(None));
+		let _ = LOG_CHANNEL.0.send(LogUpdate::Operation(None));
+	}
+}
+
+// None hides the bar
+#[component]
+pub fn ProgressBar(class: Option<String>, progress: SyncSignal<Option<f32>>) -> Element {
+	let class = class.unwrap_or_default();
+	rsx! {
+		if let Some(fraction) = *progress.read() {
+			div { class: "{class} h-2 w-full rounded bg-gray-700 overflow-hidden",
+				div {
+					class: "h-full bg-gray-300",
+					style: "width: {fraction * 100.0}%;"
+				}
+			}
+		}
+	}
 }
 
 struct InfoLog {
@@ -118,7 +293,8 @@ impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for InfoLog {
 
 		let update = match *event.metadata().level() {
 			tracing::Level::ERROR => LogUpdate::Error(message),
-			tracing::Level::INFO => LogUpdate::Info(message),
+			// warnings aren't fatal, so they go on the info line rather than the error one
+			tracing::Level::WARN | tracing::Level::INFO => LogUpdate::Info(message),
 			_ => {
 				return;
 			}
@@ -128,26 +304,124 @@ impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for InfoLog {
 }
 
 #[component]
-pub fn InfoPanel(class: Option<String>, style: Option<String>) -> Element {
+pub fn InfoPanel(
+	class: Option<String>,
+	style: Option<String>,
+	config: ReadOnlySignal<Config, SyncStorage>,
+) -> Element {
 	let class = class.unwrap_or_default();
 	let mut last_error = use_signal(|| "".into());
 	let mut last_info = use_signal(|| "".into());
+	// bumped on every info message so a pending clear knows it has been superseded
+	let mut info_generation = use_signal(|| 0u64);
+	let mut current_operation = use_signal(|| None::<Box<str>>);
+	let mut notice = use_signal(|| None::<Box<str>>);
+	let mut progress = use_signal_sync(|| None::<f32>);
+	// the token of the latest cancellable operation, cancelled by the Cancel button
+	let mut cancellable = use_signal(|| None::<CancelToken>);
+	// (is_error, message), newest last
+	let mut history = use_signal(VecDeque::<(bool, Box<str>)>::new);
+	let mut expanded = use_signal(|| false);
+	let mut push_history = move |is_error: bool, message: Box<str>| {
+		let mut history = history.write();
+		if history.len() == HISTORY_LEN {
+			history.pop_front();
+		}
+		history.push_back((is_error, message));
+	};
 
 	use_future(move || async move {
 		let mut rx = LOG_CHANNEL.1.resubscribe();
-		while let Ok(udpate) = rx.recv().await {
-			match udpate {
+		loop {
+			let update = match rx.recv().await {
+				Ok(update) => update,
+				// missing a few progress updates is fine, just keep going from the latest
+				Err(broadcast::error::RecvError::Lagged(_)) => continue,
+				Err(broadcast::error::RecvError::Closed) => break,
+			};
+			match update {
 				LogUpdate::Info(info) => {
+					push_history(false, info.clone());
 					last_info.set(info);
+					info_generation += 1;
+					// errors are left alone so they can't be missed
+					if let Some(retention) = config.read().get_info_retention() {
+						let generation = *info_generation.peek();
+						spawn(async move {
+							tokio::time::sleep(retention).await;
+							if *info_generation.peek() == generation {
+								last_info.set("".into());
+							}
+						});
+					}
 				}
 				LogUpdate::Error(error) => {
+					push_history(true, error.clone());
 					last_error.set(error);
 				}
+				LogUpdate::Operation(operation) => {
+					current_operation.set(operation);
+				}
+				LogUpdate::Progress(fraction) => {
+					progress.set(fraction);
+				}
+				LogUpdate::Cancellable(cancel) => {
+					cancellable.set(Some(cancel));
+				}
+				// another operation may have become the one the button is shown for since
+				LogUpdate::CancellableEnded(cancel) => {
+					if cancellable
+						.peek()
+						.as_ref()
+						.is_some_and(|current| current.is_same(&cancel))
+					{
+						cancellable.set(None);
+					}
+				}
+				LogUpdate::Notice(new_notice) => {
+					notice.set(new_notice);
+				}
+				LogUpdate::ShowHistory => {
+					expanded.set(true);
+				}
 			}
 		}
 	});
 	rsx! {
-		div { class: "{class} info-panel", style,
+		div {
+			class: "{class} info-panel relative",
+			style,
+			onclick: move |_| expanded.toggle(),
+			if *expanded.read() {
+				// column-reverse keeps the view pinned to the newest message as more arrive
+				div { class: "absolute bottom-full left-0 w-full h-64 overflow-y-auto flex flex-col-reverse rounded bg-gray-900/90 p-2",
+					for (i, (is_error, message)) in history.read().iter().enumerate().rev() {
+						div { key: "{i}", class: if *is_error { "text-red-400" } else { "" }, "{message}" }
+					}
+				}
+			}
+			{current_operation.read().as_ref().map(|operation| rsx! {
+				div { class: "flex justify-between",
+					div { class: "italic", "{operation}" }
+					if cancellable.read().is_some() {
+						button {
+							class: "underline px-2",
+							onclick: move |e| {
+								// the panel itself toggles the history on click
+								e.stop_propagation();
+								if let Some(cancel) = &*cancellable.peek() {
+									cancel.cancel();
+								}
+							},
+							{tr("cancel")}
+						}
+					}
+				}
+			})}
+			ProgressBar { progress }
+			{notice.read().as_ref().map(|notice| rsx! {
+				div { class: "text-yellow-300", "{notice}" }
+			})}
 			div { {last_info.read()} }
 			div { {last_error.read()} }
 		}
@@ -1,29 +1,50 @@
 #![cfg_attr(feature = "bundle", windows_subsystem = "windows")]
 
+use crate::about::AboutButton;
 use crate::button::{
-	LaunchButton, Run4GBPatcherButton, RunPreloadPatcherButton, SetGameLocationButton,
+	Check4GBPatchButton, LaunchButton, LaunchMethodToggle, PatchStatusBadge, Run4GBPatcherButton,
+	RunPreloadPatcherButton, SetGameLocationButton,
 };
+use crate::i18n::tr;
 use crate::log::InfoPanel;
+use crate::modal::OpenModals;
+use crate::network::OfflineIndicator;
+use crate::settings::SettingsButton;
+use crate::tray::Tray;
 use crate::update::UpdateButton;
 use anyhow::Result;
 use button::DonateButton;
 use config::Config;
-use dioxus::desktop::tao::platform::windows::{IconExtWindows, WindowBuilderExtWindows};
-use dioxus::desktop::LogicalSize;
+#[cfg(target_os = "windows")]
+use dioxus::desktop::tao::{
+	platform::windows::{IconExtWindows, WindowBuilderExtWindows},
+	window::Icon,
+};
+use dioxus::desktop::{LogicalSize, WindowCloseBehaviour};
 use dioxus::{
-	desktop::{
-		tao::{dpi::Size, window::Icon},
-		WindowBuilder,
-	},
+	desktop::{tao::dpi::Size, WindowBuilder},
 	prelude::*,
 };
+use std::path::Path;
+use std::process::ExitCode;
+mod about;
 mod button;
+mod cli;
 mod config;
+mod generated_mod;
+mod i18n;
 mod log;
+mod mod_list;
+mod modal;
+mod network;
 mod patcher_laa;
 mod patcher_preload;
-mod sq;
+mod process;
+mod settings;
 mod steamless;
+#[cfg(test)]
+mod temp_dir;
+mod tray;
 mod update;
 
 #[derive(Clone, Routable, Debug, PartialEq)]
@@ -37,15 +58,20 @@ const ASSETS: &str = "assets";
 #[cfg(not(feature = "bundle"))]
 const ASSETS: &str = "assets/assets";
 
-fn build_window() -> WindowBuilder {
-	WindowBuilder::new()
-		.with_maximizable(false)
-		.with_resizable(false)
-		.with_inner_size(Size::Logical(LogicalSize {
-			width: 1024.0,
-			height: 768.0,
-		}))
-		.with_title("MSU Launcher")
+// the window comes up unstyled without these, which almost always means the zip wasn't fully extracted
+const REQUIRED_ASSETS: [&str; 3] = ["style/tailwind.css", "main.css", "gfx/icons/msu_logo.ico"];
+
+fn find_missing_assets() -> Vec<String> {
+	REQUIRED_ASSETS
+		.iter()
+		.map(|asset| format!("{}/{}", ASSETS, asset))
+		.filter(|path| !Path::new(path).exists())
+		.collect()
+}
+
+#[cfg(target_os = "windows")]
+fn with_icons(window: WindowBuilder) -> WindowBuilder {
+	window
 		.with_window_icon(
 			Icon::from_path(
 				format!("{}/gfx/icons/msu_logo.ico", ASSETS),
@@ -62,10 +88,39 @@ fn build_window() -> WindowBuilder {
 		)
 }
 
-fn main() {
+// the .ico can only be loaded from a path on Windows, other platforms use the default icon
+#[cfg(not(target_os = "windows"))]
+fn with_icons(window: WindowBuilder) -> WindowBuilder {
+	window
+}
+
+fn build_window() -> WindowBuilder {
+	with_icons(
+		WindowBuilder::new()
+			.with_maximizable(false)
+			.with_resizable(false)
+			.with_inner_size(Size::Logical(LogicalSize {
+				width: 1024.0,
+				height: 768.0,
+			}))
+			.with_title("MSU Launcher"),
+	)
+}
+
+fn main() -> ExitCode {
+	let config = Config::load_or_default();
 	// Init logger
-	once_cell::sync::Lazy::force(&log::TRACING);
+	log::init(config.get_log_rotation(), config.json_logs());
+	if let Some(exit_code) = cli::run(&config) {
+		log::flush();
+		return exit_code;
+	}
 	tracing::info!("Starting MSU Launcher");
+	i18n::init(config.get_lang());
+	let missing_assets = find_missing_assets();
+	if !missing_assets.is_empty() {
+		tracing::error!("Missing assets: {}", missing_assets.join(", "));
+	}
 	let cfg = dioxus::desktop::Config::new()
 		.with_custom_head(
 			r#"
@@ -74,19 +129,28 @@ fn main() {
 		"#
 			.to_string(),
 		)
-		.with_window(build_window());
-	LaunchBuilder::desktop().with_cfg(cfg).launch(App);
+		.with_window(build_window())
+		// the Tray switches this to hiding once its icon exists
+		.with_close_behaviour(WindowCloseBehaviour::LastWindowExitsApp);
+	LaunchBuilder::desktop()
+		.with_cfg(cfg)
+		.with_context(config)
+		.launch(App);
+	ExitCode::SUCCESS
 }
 
 #[component]
-fn Header(style: Option<String>) -> Element {
+fn Header(style: Option<String>, config: SyncSignal<Config>) -> Element {
 	let style = style.unwrap_or_default();
 	rsx! {
 		div {
 			class: "w-full flex justify-center items-center relative",
 			style,
 			DonateButton { class: "left-3 top-3 absolute" }
-			UpdateButton { class: "right-3 top-3 h-16 absolute normal-font max-w-52" }
+			AboutButton { class: "left-48 top-5 absolute p-1 normal-font", config }
+			SettingsButton { class: "left-72 top-5 absolute p-1 normal-font", config }
+			UpdateButton { class: "right-3 top-3 h-16 absolute normal-font max-w-52", config }
+			OfflineIndicator { class: "right-3 bottom-0 absolute", config }
 			h1 { class: "title-font text-6xl", "MSU Launcher" }
 		}
 	}
@@ -96,44 +160,107 @@ fn Header(style: Option<String>) -> Element {
 fn Center() -> Element {
 	rsx!(
 		div { class: "h-4/6 w-full flex flex-col justify-center items-center",
-			p { "Mod List Manager? Conflict Analyzer? Mod Update Checker?" }
+			p { {tr("center_placeholder")} }
 		}
 	)
 }
 
 #[component]
-fn ButtonBar() -> Element {
-	let config = use_signal_sync(Config::load_or_default);
+fn ButtonBar(config: SyncSignal<Config>) -> Element {
+	// explain why the game couldn't be found automatically, this runs after the InfoPanel has subscribed
+	use_future(move || async move {
+		// config.toml was already loaded while the logger was set up, before anything could show it
+		if let Some(warning) = config::take_config_reset_warning() {
+			tracing::error!("{}", warning);
+		}
+		if config.read().bb_path_known() {
+			return;
+		}
+		if let Err(e) = config::find_bb() {
+			tracing::error!("{:#}", anyhow::Error::new(e));
+		}
+	});
 	rsx!(
 		div { class: "flex h-fit justify-between items-center space-x-2 w-[90%]",
 			SetGameLocationButton { class: "p-1 text-xl normal-font", config }
-			LaunchButton { class: "flex-grow h-full text-4xl title-font", config }
+			div { class: "flex flex-col flex-grow h-full space-y-1",
+				LaunchButton { class: "flex-grow text-4xl title-font", config }
+				LaunchMethodToggle { class: "p-1 text-sm normal-font", config }
+			}
 			div { class: "flex flex-col space-y-1",
-				RunPreloadPatcherButton { class: "p-1 h-1/2 text-xl normal-font", config }
-				Run4GBPatcherButton { class: "p-1 h-1/2 text-xl normal-font", config }
+				RunPreloadPatcherButton { class: "p-1 h-1/3 text-xl normal-font", config }
+				Run4GBPatcherButton { class: "p-1 h-1/3 text-xl normal-font", config }
+				Check4GBPatchButton { class: "p-1 h-1/3 text-xl normal-font", config }
+				PatchStatusBadge { class: "text-sm normal-font text-center", config }
 			}
 		}
 	)
 }
 
 #[component]
-fn Content(style: Option<String>) -> Element {
+fn Content(style: Option<String>, config: SyncSignal<Config>) -> Element {
 	let style = style.unwrap_or_default();
 	rsx!(
 		div {
 			class: "flex flex-col h-full w-full justify-center items-center",
 			style,
 			Center {}
-			InfoPanel { class: "w-[90%] h-12 mb-4" }
-			ButtonBar {}
+			InfoPanel { class: "w-[90%] h-16 mb-4", config }
+			ButtonBar { config }
+		}
+	)
+}
+
+// Enter launches the game, Ctrl+P runs the preload patcher and Ctrl+G runs the 4GB patcher
+// a panel on top takes the keyboard, e.g. Enter on a settings checkbox mustn't launch the game
+fn handle_shortcut(e: KeyboardEvent, config: SyncSignal<Config>, open_modals: OpenModals) {
+	if e.is_auto_repeating() || open_modals.any() || !config.read().bb_path_known() {
+		return;
+	}
+	let ctrl = e.modifiers().contains(Modifiers::CONTROL);
+	match e.key() {
+		Key::Enter if !ctrl => button::spawn_launch_game(config),
+		Key::Character(c) if ctrl && c.eq_ignore_ascii_case("p") => {
+			button::spawn_preload_patcher(config.into())
+		}
+		Key::Character(c) if ctrl && c.eq_ignore_ascii_case("g") => {
+			button::spawn_4gb_patcher(config)
+		}
+		_ => {}
+	}
+}
+
+// styled inline since the stylesheets may be among the missing files
+#[component]
+fn MissingAssetsBanner(missing_assets: Vec<String>) -> Element {
+	rsx!(
+		div { style: "position: fixed; top: 0; left: 0; right: 0; z-index: 30; padding: 8px; background: #7f1d1d; color: white; font-family: sans-serif;",
+			p { {tr("missing_assets")} }
+			p { style: "font-size: small;", {missing_assets.join(", ")} }
 		}
 	)
 }
 
 #[component]
 fn App() -> Element {
+	// the config main loaded, from here on the signal is the one that's kept up to date
+	let config = use_signal_sync(consume_context::<Config>);
+	let missing_assets = use_hook(find_missing_assets);
+	let open_modals = modal::use_open_modals_provider();
+	// the window closing, or Quit in the tray menu, drops the app before the process ends
+	use_drop(log::flush);
 	rsx! {
-		Header { style: "height: 10.4%;" }
-		Content { style: "height: 89.6%;" }
+		if !missing_assets.is_empty() {
+			MissingAssetsBanner { missing_assets }
+		}
+		div {
+			class: "flex flex-col h-full w-full justify-center items-center outline-none",
+			tabindex: 0,
+			autofocus: true,
+			onkeydown: move |e| handle_shortcut(e, config, open_modals),
+			Tray { config }
+			Header { style: "height: 10.4%;", config }
+			Content { style: "height: 89.6%;", config }
+		}
 	}
 }
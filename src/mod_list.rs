@@ -0,0 +1,162 @@
+use dioxus::prelude::*;
+
+use crate::{
+	button::Button,
+	config::Config,
+	i18n::tr,
+	log, modal,
+	patcher_preload::{self, ModInfo},
+	update::{self, ModUpdate},
+};
+
+async fn load_mods(config: ReadOnlySignal<Config, SyncStorage>) -> Result<Vec<ModInfo>, String> {
+	let mods_path = config
+		.read()
+		.get_mods_path()
+		.ok_or_else(|| tr("mod_list_no_mods_folder").to_string())?;
+	match tokio::task::spawn_blocking(move || patcher_preload::list_mods_with_metadata(&mods_path))
+		.await
+	{
+		Ok(Ok(mods)) => Ok(mods),
+		Ok(Err(e)) => Err(format!("{:#}", e)),
+		Err(e) => Err(e.to_string()),
+	}
+}
+
+async fn check_updates(mods: Vec<ModInfo>, mut updates: Signal<Option<Vec<ModUpdate>>>) {
+	let _operation = log::Operation::start("Checking mods for updates...");
+	match update::check_mod_updates(&mods).await {
+		Ok(found) => {
+			tracing::info!("Found updates for {} mods", found.len());
+			updates.set(Some(found));
+		}
+		Err(e) => tracing::error!("Failed to check mods for updates: {:#}", e),
+	}
+}
+
+#[component]
+fn ModListRow(info: ModInfo, update: Option<ModUpdate>) -> Element {
+	let mut show_changelog = use_signal(|| false);
+	let metadata = info.metadata.unwrap_or_default();
+	rsx!(
+		tr {
+			td { class: "pr-4", "{info.file_name}" }
+			td { class: "pr-4", {metadata.name.unwrap_or_default()} }
+			td { class: "pr-4", {metadata.version.unwrap_or_default()} }
+			td {
+				{update.as_ref().map(|update| rsx!(
+					button {
+						class: "underline text-yellow-300",
+						onclick: move |_| show_changelog.toggle(),
+						{format!("{} {}", update.release.version, tr("mod_list_update_available"))}
+					}
+				))}
+			}
+		}
+		if *show_changelog.read() {
+			{update.map(|update| {
+				let changelog = update.release.changelog.unwrap_or_else(|| tr("mod_list_no_changelog").to_string());
+				let url = update.release.url;
+				rsx!(
+					tr {
+						td { colspan: "4",
+							pre { class: "rounded bg-gray-900 p-2 text-sm whitespace-pre-wrap", "{changelog}" }
+							Button {
+								class: "p-1",
+								onclick: move |_| match open::that(&url) {
+									Ok(_) => tracing::info!("Opened release page"),
+									Err(e) => tracing::error!("Failed to open release page: {}", e),
+								},
+								{tr("mod_list_open_release")}
+							}
+						}
+					}
+				)
+			})}
+		}
+	)
+}
+
+#[component]
+fn ModListPanel(
+	config: ReadOnlySignal<Config, SyncStorage>,
+	on_close: EventHandler<MouseEvent>,
+) -> Element {
+	modal::use_modal();
+	let mods = use_resource(move || load_mods(config));
+	// None until checked, the check only covers MSU mods that say where they're released
+	let updates = use_signal(|| None::<Vec<ModUpdate>>);
+	let offline = use_memo(move || !config.read().is_online());
+	let find_update = move |file_name: &str| {
+		updates.read().as_ref().and_then(|updates| {
+			updates
+				.iter()
+				.find(|update| update.file_name == file_name)
+				.cloned()
+		})
+	};
+	let contents = match &*mods.read() {
+		None => rsx!( p { {tr("mod_list_loading")} } ),
+		Some(Err(e)) => rsx!( p { "{e}" } ),
+		Some(Ok(mods)) if mods.is_empty() => rsx!( p { {tr("mod_list_empty")} } ),
+		Some(Ok(mods)) => rsx!(
+			table { class: "text-left text-base",
+				thead {
+					tr { class: "text-gray-300",
+						th { {tr("mod_list_file")} }
+						th { {tr("mod_list_name")} }
+						th { {tr("mod_list_version")} }
+						th {}
+					}
+				}
+				tbody {
+					for info in mods.iter() {
+						ModListRow { key: "{info.file_name}", info: info.clone(), update: find_update(&info.file_name) }
+					}
+				}
+			}
+			Button {
+				class: "p-1 mt-2",
+				disabled: offline,
+				onclick: {
+					let mods = mods.clone();
+					move |_| {
+						spawn(check_updates(mods.clone(), updates));
+					}
+				},
+				{tr("mod_list_check_updates")}
+			}
+		),
+	};
+	// drawn over the settings panel it's opened from
+	rsx!(
+		div { class: "fixed inset-0 z-20 flex justify-center items-center bg-black/70",
+			div { class: "flex flex-col space-y-2 rounded-lg bg-gray-800 p-6 normal-font text-lg w-[90%] max-h-[90%]",
+				h2 { class: "title-font text-3xl text-center", {tr("mod_list")} }
+				div { class: "overflow-y-auto", {contents} }
+				Button { class: "p-1 text-xl", onclick: move |e| on_close.call(e), {tr("close")} }
+			}
+		}
+	)
+}
+
+#[component]
+pub fn ShowModListButton(
+	class: Option<String>,
+	style: Option<String>,
+	config: ReadOnlySignal<Config, SyncStorage>,
+) -> Element {
+	let mut open = use_signal(|| false);
+	rsx!(
+		Button {
+			class,
+			style,
+			disabled: use_memo(move || !config.read().bb_path_known()),
+			onclick: move |_| open.set(true),
+			{tr("show_mod_list")}
+		}
+		if *open.read() {
+			ModListPanel { config, on_close: move |_| open.set(false) }
+		}
+	)
+}
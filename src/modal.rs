@@ -0,0 +1,22 @@
+use dioxus::prelude::*;
+
+// how many panels are drawn over the launcher, the keyboard shortcuts are off while any are
+#[derive(Clone, Copy)]
+pub struct OpenModals(Signal<usize>);
+
+pub fn use_open_modals_provider() -> OpenModals {
+	use_context_provider(|| OpenModals(Signal::new(0)))
+}
+
+impl OpenModals {
+	pub fn any(&self) -> bool {
+		*self.0.read() > 0
+	}
+}
+
+// called by every panel drawn over the launcher, counted for as long as it's mounted
+pub fn use_modal() {
+	let OpenModals(mut open) = use_context::<OpenModals>();
+	use_hook(move || *open.write() += 1);
+	use_drop(move || *open.write() -= 1);
+}
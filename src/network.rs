@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use dioxus::prelude::*;
+
+use crate::{config::Config, i18n::tr};
+
+const CONNECTIVITY_URL: &str = "https://github.com";
+const CONNECTIVITY_TIMEOUT: Duration = Duration::from_secs(5);
+
+// any response at all means we're online, we only care about failing to connect
+pub async fn is_online() -> bool {
+	let client = match reqwest::Client::builder()
+		.timeout(CONNECTIVITY_TIMEOUT)
+		.build()
+	{
+		Ok(client) => client,
+		Err(e) => {
+			tracing::warn!("Couldn't build reqwest agent for connectivity check: {}", e);
+			return false;
+		}
+	};
+	client.head(CONNECTIVITY_URL).send().await.is_ok()
+}
+
+pub async fn refresh_online_status(mut config: SyncSignal<Config>) -> bool {
+	let online = is_online().await;
+	config.with_mut(|c| c.set_online(online));
+	online
+}
+
+#[component]
+pub fn OfflineIndicator(
+	#[props(default = "".to_string())] class: String,
+	style: Option<String>,
+	config: ReadOnlySignal<Config, SyncStorage>,
+) -> Element {
+	rsx!(
+		if !config.read().is_online() {
+			div { class: "{class} normal-font rounded-lg bg-gray-800 px-2 py-1", style, {tr("offline")} }
+		}
+	)
+}
@@ -1,14 +1,24 @@
-use crate::config::Config;
+use crate::{config::Config, process, steamless};
 use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
 use dioxus::prelude::*;
 use sha2::{Digest, Sha256};
 use std::collections::HashSet;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::mem::size_of;
 use std::process::Command;
-use std::{fs::File, path::Path};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use std::{
+	fs::File,
+	path::{Path, PathBuf},
+};
 use windows::Win32::System::Diagnostics::Debug::{
 	IMAGE_FILE_CHARACTERISTICS, IMAGE_FILE_HEADER, IMAGE_FILE_LARGE_ADDRESS_AWARE,
+	IMAGE_SECTION_HEADER,
+};
+use windows::Win32::System::SystemInformation::{
+	IMAGE_FILE_MACHINE_AMD64, IMAGE_FILE_MACHINE_I386,
 };
 use windows::Win32::System::SystemServices::IMAGE_DOS_HEADER;
 
@@ -26,12 +36,32 @@ fn get_hash_set_from_str(hash_str: &str) -> HashSet<Vec<u8>> {
 		.collect()
 }
 
-fn remove_steam_drm(original_path: &Path) -> Result<()> {
+// the game itself or an antivirus scan holding the exe open is the usual cause of a failed patch
+fn explain_locked_exe(error: std::io::Error) -> anyhow::Error {
+	if process::is_file_locked(&error) {
+		anyhow!("BattleBrothers.exe is in use — close the game and any antivirus scan, then retry.")
+	} else {
+		anyhow!(error)
+	}
+}
+
+// kept next to the original so the game's dlls are still found, Steam only ever replaces BattleBrothers.exe
+const UNPACKED_EXE_NAME: &str = "BattleBrothersUnpacked.exe";
+
+pub fn unpacked_exe_path(exe_path: &Path) -> PathBuf {
+	exe_path.with_file_name(UNPACKED_EXE_NAME)
+}
+
+// the unpacked exe ends up at target_path, which may be the original itself
+fn remove_steam_drm(original_path: &Path, target_path: &Path, steamless_path: &Path) -> Result<()> {
+	let _span = tracing::info_span!("remove_steam_drm").entered();
 	// bad approach, want to improve this by using the steamless API dlls
 	// or ideally dll injection as suggested by MonochromeWench
-	let out = Command::new("./steamless/Steamless.CLI.exe")
+	let cli_path = steamless::cli_path(steamless_path)?;
+	let out = Command::new(&cli_path)
 		.arg(original_path)
-		.output()?;
+		.output()
+		.with_context(|| format!("Couldn't run {}", cli_path.display()))?;
 	match out.status.code() {
 		Some(0) => Ok(()),
 		Some(code) => Err(anyhow!("Steamless failed with code {}", code)),
@@ -48,7 +78,8 @@ fn remove_steam_drm(original_path: &Path) -> Result<()> {
 		return Err(anyhow!("Steamless didn't create a new file"));
 	}
 
-	std::fs::rename(new_str, original_path)?;
+	std::fs::rename(new_str, target_path).map_err(explain_locked_exe)?;
+	forget_exe_hash();
 	Ok(())
 }
 
@@ -118,16 +149,92 @@ fn write_image_file_header(file: &mut File, header: &IMAGE_FILE_HEADER) -> Resul
 	Ok(())
 }
 
-fn make_laa(path: &Path) -> Result<()> {
-	let mut file = File::options().read(true).write(true).open(path)?;
+fn set_laa(path: &Path, enabled: bool) -> Result<()> {
+	let mut file = File::options()
+		.read(true)
+		.write(true)
+		.open(path)
+		.map_err(explain_locked_exe)?;
 	seek_to_pe_header(&mut file)?;
 	let mut file_header = read_image_file_header(&mut file)?;
-	file_header.Characteristics |= IMAGE_FILE_LARGE_ADDRESS_AWARE;
+	if enabled {
+		file_header.Characteristics |= IMAGE_FILE_LARGE_ADDRESS_AWARE;
+	} else {
+		file_header.Characteristics &= !IMAGE_FILE_LARGE_ADDRESS_AWARE;
+	}
 	seek_to_pe_header(&mut file)?;
 	write_image_file_header(&mut file, &file_header)?;
+	// only a bit changes, the size stays the same and the mtime may too
+	forget_exe_hash();
 	Ok(())
 }
 
+fn make_laa(path: &Path) -> Result<()> {
+	let _span = tracing::info_span!("make_laa").entered();
+	set_laa(path, true)
+}
+
+fn remove_laa(path: &Path) -> Result<()> {
+	let _span = tracing::info_span!("remove_laa").entered();
+	set_laa(path, false)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Architecture {
+	X86,
+	X64,
+	Other(u16),
+}
+
+impl std::fmt::Display for Architecture {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::X86 => f.write_str("32-bit"),
+			Self::X64 => f.write_str("64-bit"),
+			Self::Other(machine) => write!(f, "unknown architecture ({:#06X})", machine),
+		}
+	}
+}
+
+// the 4GB patch only makes sense for a 32-bit exe, a 64-bit one can already use all the memory
+pub fn detect_architecture(path: &Path) -> Result<Architecture> {
+	let mut file = File::open(path)?;
+	seek_to_pe_header(&mut file)?;
+	let machine = read_image_file_header(&mut file)?.Machine;
+	Ok(if machine == IMAGE_FILE_MACHINE_I386 {
+		Architecture::X86
+	} else if machine == IMAGE_FILE_MACHINE_AMD64 {
+		Architecture::X64
+	} else {
+		Architecture::Other(machine.0)
+	})
+}
+
+// SteamStub adds its loader in a section of its own, which Steamless removes again
+const STEAM_STUB_SECTION: &[u8; 8] = b".bind\0\0\0";
+
+// a heuristic for Steam versions that aren't in hashes/ yet, e.g. right after a game update
+pub fn has_steam_drm(path: &Path) -> Result<bool> {
+	let mut file = File::open(path)?;
+	seek_to_pe_header(&mut file)?;
+	let file_header = read_image_file_header(&mut file)?;
+	// the section table follows the optional header
+	file.seek(SeekFrom::Current(file_header.SizeOfOptionalHeader as i64))?;
+	for _ in 0..file_header.NumberOfSections {
+		let mut section_header = IMAGE_SECTION_HEADER::default();
+		file.read_exact(unsafe {
+			std::slice::from_raw_parts_mut(
+				std::ptr::from_mut(&mut section_header) as *mut u8,
+				size_of::<IMAGE_SECTION_HEADER>(),
+			)
+		})?;
+		if &section_header.Name == STEAM_STUB_SECTION {
+			return Ok(true);
+		}
+	}
+	Ok(false)
+}
+
 pub fn is_laa(path: &Path) -> Result<bool> {
 	let mut file = File::open(path)?;
 	seek_to_pe_header(&mut file)?;
@@ -143,13 +250,85 @@ fn sha_hash_path(path: &Path) -> Result<Vec<u8>> {
 	Ok(hasher.finalize().to_vec())
 }
 
-fn make_backup(path: &Path, backup_extension: &str) -> Result<()> {
-	let backup_path = format!(
-		"{}.{}",
-		path.to_str()
-			.with_context(|| format!("Couldn't parse file path {:?}", path))?,
-		backup_extension
-	);
+// hashing the whole exe takes a moment, so the result is kept until the file changes
+// FAT and some network shares only keep the mtime to the second or two, so the patcher's own writes
+// clear it, and patch_exe never relies on it
+static EXE_HASH_CACHE: Mutex<Option<(PathBuf, SystemTime, u64, String)>> = Mutex::new(None);
+
+// in the same format as the files in hashes/, so an unknown version can be added to them as is
+pub fn exe_hash_hex(exe_path: &Path) -> Result<String> {
+	let metadata = std::fs::metadata(exe_path)?;
+	let (modified, len) = (metadata.modified()?, metadata.len());
+	let mut cache = EXE_HASH_CACHE
+		.lock()
+		.map_err(|_| anyhow!("Exe hash cache is poisoned"))?;
+	if let Some((path, cached_modified, cached_len, hash)) = &*cache {
+		if path == exe_path && *cached_modified == modified && *cached_len == len {
+			return Ok(hash.clone());
+		}
+	}
+	let hash = const_hex::encode_upper(sha_hash_path(exe_path)?);
+	*cache = Some((exe_path.to_path_buf(), modified, len, hash.clone()));
+	Ok(hash)
+}
+
+fn forget_exe_hash() {
+	if let Ok(mut cache) = EXE_HASH_CACHE.lock() {
+		*cache = None;
+	}
+}
+
+const BACKUP_EXTENSIONS: [&str; 3] = ["steam_backup", "steamless_backup", "gog_backup"];
+
+fn get_backup_path(
+	path: &Path,
+	backup_extension: &str,
+	backup_dir: Option<&Path>,
+) -> Result<PathBuf> {
+	let stem = match backup_dir {
+		Some(backup_dir) => {
+			let file_name = path
+				.file_name()
+				.with_context(|| format!("Couldn't get file name of {:?}", path))?
+				.to_str()
+				.with_context(|| format!("Couldn't parse file path {:?}", path))?;
+			std::fs::create_dir_all(backup_dir)
+				.with_context(|| format!("Couldn't create backup folder {:?}", backup_dir))?;
+			backup_dir.join(format!(
+				"{}.{}",
+				file_name,
+				chrono::Local::now().format("%Y%m%d_%H%M%S")
+			))
+		}
+		None => path.to_path_buf(),
+	};
+	let stem = stem
+		.to_str()
+		.with_context(|| format!("Couldn't parse file path {:?}", stem))?
+		.to_owned();
+	// never overwrite an earlier backup, the first one is the only copy of the original exe
+	let mut backup_path = PathBuf::from(format!("{}.{}", stem, backup_extension));
+	let mut number = 1;
+	while backup_path.try_exists()? {
+		backup_path = PathBuf::from(format!("{}.{}.{}", stem, number, backup_extension));
+		number += 1;
+	}
+	Ok(backup_path)
+}
+
+fn make_backup(path: &Path, backup_extension: &str, backup_dir: Option<&Path>) -> Result<()> {
+	let _span = tracing::info_span!("make_backup", extension = backup_extension).entered();
+	// an exe that was already unpacked or patched would pass itself off as the original
+	if backup_extension == "steam_backup"
+		&& !get_hash_set_from_str(STEAM_HASH_STR).contains(&sha_hash_path(path)?)
+		&& !has_steam_drm(path)?
+	{
+		return Err(anyhow!(
+			"{:?} isn't an unmodified Steam exe, refusing to back it up as the Steam original",
+			path
+		));
+	}
+	let backup_path = get_backup_path(path, backup_extension, backup_dir)?;
 	std::fs::copy(path, backup_path).with_context(move || {
 		format!(
 			"Failed to create backup of file {:?} with extension {}",
@@ -159,30 +338,420 @@ fn make_backup(path: &Path, backup_extension: &str) -> Result<()> {
 	Ok(())
 }
 
-pub fn patch_exe(exe_path: &Path) -> Result<String> {
+fn is_backup_of(candidate: &Path, file_name: &str) -> bool {
+	let Some(candidate_name) = candidate.file_name().and_then(|name| name.to_str()) else {
+		return false;
+	};
+	candidate_name.starts_with(file_name)
+		&& BACKUP_EXTENSIONS
+			.iter()
+			.any(|extension| candidate_name.ends_with(&format!(".{}", extension)))
+}
+
+// looks both next to the exe and in the backup folder, since the folder may have been configured after patching
+pub fn list_backups(exe_path: &Path, backup_dir: Option<&Path>) -> Result<Vec<PathBuf>> {
+	let file_name = exe_path
+		.file_name()
+		.and_then(|name| name.to_str())
+		.with_context(|| format!("Couldn't get file name of {:?}", exe_path))?;
+	let mut folders = vec![exe_path.parent().context("Couldn't get exe folder")?];
+	folders.extend(backup_dir);
+	let mut backups = Vec::new();
+	for folder in folders {
+		let entries = match std::fs::read_dir(folder) {
+			Ok(entries) => entries,
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+			Err(e) => return Err(e.into()),
+		};
+		backups.extend(
+			entries
+				.flatten()
+				.map(|entry| entry.path())
+				.filter(|path| is_backup_of(path, file_name)),
+		);
+	}
+	backups.sort();
+	backups.dedup();
+	Ok(backups)
+}
+
+// a previous run that failed after removing the DRM already backed up the unpacked exe
+fn has_matching_backup(
+	exe_path: &Path,
+	backup_extension: &str,
+	backup_dir: Option<&Path>,
+) -> Result<bool> {
 	let hash = sha_hash_path(exe_path)?;
-	if get_hash_set_from_str(STEAM_HASH_STR).contains(&hash) {
-		make_backup(exe_path, "steam_backup")?;
-		remove_steam_drm(exe_path).context("Failed to remove Steam DRM")?;
-		make_backup(exe_path, "steamless_backup")?;
-		make_laa(exe_path).context("Failed to apply 4GB Patch")?;
-		Ok("Patched Steam Version".to_string())
-	} else if get_hash_set_from_str(STEAMLESS_HASH_STR).contains(&hash) {
-		make_backup(exe_path, "steamless_backup")?;
-		make_laa(exe_path).context("Failed to apply 4GB Patch")?;
-		Ok("Patched Steamless Version".to_string())
-	} else if get_hash_set_from_str(GOG_HASH_STR).contains(&hash) {
-		make_backup(exe_path, "gog_backup")?;
-		make_laa(exe_path).context("Failed to apply 4GB Patch")?;
-		Ok("Patched GOG Version".to_string())
-	} else if is_laa(exe_path)? {
-		Ok("Already patched".to_string())
-	} else {
-		Err(anyhow!("Unknown version of Battle Brothers, verify or reinstall your game from a legitimate source"))
+	for backup in list_backups(exe_path, backup_dir)? {
+		let is_same_kind = backup
+			.extension()
+			.is_some_and(|extension| extension == backup_extension);
+		if is_same_kind && sha_hash_path(&backup)? == hash {
+			return Ok(true);
+		}
+	}
+	Ok(false)
+}
+
+// a steamless_backup is the unpacked exe, only these are the exe as the game shipped it
+const ORIGINAL_BACKUP_EXTENSIONS: [&str; 2] = ["steam_backup", "gog_backup"];
+
+// the newest one, an older backup may be from before a game update
+fn find_original_backup(exe_path: &Path, backup_dir: Option<&Path>) -> Result<Option<PathBuf>> {
+	let mut newest = None;
+	for backup in list_backups(exe_path, backup_dir)? {
+		let is_original = backup
+			.extension()
+			.is_some_and(|extension| ORIGINAL_BACKUP_EXTENSIONS.iter().any(|e| extension == *e));
+		if !is_original {
+			continue;
+		}
+		let modified = std::fs::metadata(&backup)?.modified()?;
+		if newest
+			.as_ref()
+			.map_or(true, |(newest, _)| modified >= *newest)
+		{
+			newest = Some((modified, backup));
+		}
 	}
+	Ok(newest.map(|(_, backup)| backup))
+}
+
+// puts the unpatched exe back, returning the backup it came from
+pub fn restore_backup(exe_path: &Path, backup_dir: Option<&Path>) -> Result<PathBuf> {
+	let backup_path = find_original_backup(exe_path, backup_dir)?
+		.with_context(|| format!("No backup of {:?} to restore", exe_path))?;
+	std::fs::copy(&backup_path, exe_path)
+		.map_err(explain_locked_exe)
+		.with_context(|| {
+			format!(
+				"Failed to restore backup {:?} to {:?}",
+				backup_path, exe_path
+			)
+		})?;
+	forget_exe_hash();
+	Ok(backup_path)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchOutcome {
+	PatchedSteam,
+	PatchedUnrecognizedSteam,
+	PatchedUnpackedCopy,
+	PatchedSteamless,
+	PatchedGog,
+	AlreadyPatched,
+}
+
+impl std::fmt::Display for PatchOutcome {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(match self {
+			Self::PatchedSteam => "Patched Steam Version",
+			Self::PatchedUnrecognizedSteam => {
+				"Patched unrecognized Steam Version, please report its hash from the log"
+			}
+			Self::PatchedUnpackedCopy => "Patched Steam Version into BattleBrothersUnpacked.exe",
+			Self::PatchedSteamless => "Patched Steamless Version",
+			Self::PatchedGog => "Patched GOG Version",
+			Self::AlreadyPatched => "Already patched",
+		})
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edition {
+	Steam,
+	Steamless,
+	Gog,
 }
 
-pub fn patch_from_config(config: ReadOnlySignal<Config, SyncStorage>) -> Result<()> {
+impl std::fmt::Display for Edition {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(match self {
+			Self::Steam => "Steam",
+			Self::Steamless => "Steamless",
+			Self::Gog => "GOG",
+		})
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExeClassification {
+	Known(Edition),
+	// hash in the same format as exe_hash_hex, for users to report so it can be added to hashes/
+	Unknown { hash: String },
+}
+
+// checks the exe against every hash set in one pass over the file
+pub fn classify_exe(exe_path: &Path) -> Result<ExeClassification> {
+	classify_hash(exe_hash_hex(exe_path)?)
+}
+
+// what to back up and whether to run Steamless is decided from this, so it always rehashes
+fn classify_exe_uncached(exe_path: &Path) -> Result<ExeClassification> {
+	classify_hash(const_hex::encode_upper(sha_hash_path(exe_path)?))
+}
+
+fn classify_hash(hash_hex: String) -> Result<ExeClassification> {
+	let hash = const_hex::decode(&hash_hex)?;
+	let edition = [
+		(STEAM_HASH_STR, Edition::Steam),
+		(STEAMLESS_HASH_STR, Edition::Steamless),
+		(GOG_HASH_STR, Edition::Gog),
+	]
+	.into_iter()
+	.find(|(hash_str, _)| get_hash_set_from_str(hash_str).contains(&hash))
+	.map(|(_, edition)| edition);
+	Ok(match edition {
+		Some(edition) => ExeClassification::Known(edition),
+		None => ExeClassification::Unknown { hash: hash_hex },
+	})
+}
+
+// None for unknown exes, which includes any that have already been patched
+pub fn detect_edition(exe_path: &Path) -> Result<Option<Edition>> {
+	Ok(match classify_exe(exe_path)? {
+		ExeClassification::Known(edition) => Some(edition),
+		ExeClassification::Unknown { .. } => None,
+	})
+}
+
+// only the Steam version has to go through Steamless before it can be patched
+// an unrecognized exe still counts when it carries the Steam DRM
+pub fn is_steam_exe(exe_path: &Path) -> Result<bool> {
+	Ok(match classify_exe(exe_path)? {
+		ExeClassification::Known(edition) => edition == Edition::Steam,
+		ExeClassification::Unknown { .. } => has_steam_drm(exe_path)?,
+	})
+}
+
+fn patch_steam_exe(
+	exe_path: &Path,
+	keep_original: bool,
+	steamless_path: &Path,
+	backup: impl Fn(&str) -> Result<()>,
+) -> Result<PatchOutcome> {
+	if keep_original {
+		let unpacked_path = unpacked_exe_path(exe_path);
+		remove_steam_drm(exe_path, &unpacked_path, steamless_path)
+			.context("Failed to remove Steam DRM")?;
+		make_laa(&unpacked_path).context("Failed to apply 4GB Patch")?;
+		return Ok(PatchOutcome::PatchedUnpackedCopy);
+	}
+	backup("steam_backup")?;
+	remove_steam_drm(exe_path, exe_path, steamless_path).context("Failed to remove Steam DRM")?;
+	backup("steamless_backup")?;
+	make_laa(exe_path).context("Failed to apply 4GB Patch")?;
+	Ok(PatchOutcome::PatchedSteam)
+}
+
+// with keep_original the Steam version is left untouched and the patched exe is written to unpacked_exe_path
+// steamless_path is the configured Steamless folder, only used for the Steam version
+pub fn patch_exe(
+	exe_path: &Path,
+	backup_dir: Option<&Path>,
+	make_backups: bool,
+	keep_original: bool,
+	steamless_path: &Path,
+) -> Result<PatchOutcome> {
+	let span = tracing::info_span!("patch_exe", edition = tracing::field::Empty);
+	let _enter = span.enter();
+	let classification = classify_exe_uncached(exe_path)?;
+	if let ExeClassification::Known(edition) = &classification {
+		span.record("edition", tracing::field::display(edition));
+	}
+	let backup = |extension: &str| {
+		if make_backups {
+			make_backup(exe_path, extension, backup_dir)
+		} else {
+			Ok(())
+		}
+	};
+	match classification {
+		ExeClassification::Known(Edition::Steam) => {
+			patch_steam_exe(exe_path, keep_original, steamless_path, backup)
+		}
+		ExeClassification::Known(Edition::Steamless) => {
+			if has_matching_backup(exe_path, "steamless_backup", backup_dir)? {
+				tracing::info!("DRM was already removed by a previous run, only applying the 4GB Patch");
+			} else {
+				backup("steamless_backup")?;
+			}
+			make_laa(exe_path).context("Failed to apply 4GB Patch")?;
+			Ok(PatchOutcome::PatchedSteamless)
+		}
+		ExeClassification::Known(Edition::Gog) => {
+			backup("gog_backup")?;
+			make_laa(exe_path).context("Failed to apply 4GB Patch")?;
+			Ok(PatchOutcome::PatchedGog)
+		}
+		ExeClassification::Unknown { .. } if is_laa(exe_path)? => Ok(PatchOutcome::AlreadyPatched),
+		ExeClassification::Unknown { hash } if has_steam_drm(exe_path)? => {
+			tracing::warn!(
+				"BattleBrothers.exe isn't a known Steam version but has the Steam DRM, trying Steamless on it anyway. Please report this hash: {}",
+				hash
+			);
+			patch_steam_exe(exe_path, keep_original, steamless_path, backup).map(|outcome| match outcome {
+				PatchOutcome::PatchedSteam => PatchOutcome::PatchedUnrecognizedSteam,
+				outcome => outcome,
+			})
+		}
+		ExeClassification::Unknown { hash } => Err(anyhow!(
+			"Your BattleBrothers.exe isn't recognized, it may be a new game version. Please report this hash: {} (otherwise verify or reinstall your game from a legitimate source)",
+			hash
+		)),
+	}
+}
+
+// deleted even when a step of the self-test fails
+struct TempCopy(PathBuf);
+
+impl Drop for TempCopy {
+	fn drop(&mut self) {
+		let _ = std::fs::remove_file(&self.0);
+	}
+}
+
+fn expect_laa(path: &Path, expected: bool) -> Result<()> {
+	if is_laa(path)? != expected {
+		return Err(anyhow!(
+			"4GB flag should be {} but isn't",
+			if expected { "set" } else { "cleared" }
+		));
+	}
+	Ok(())
+}
+
+// toggles the 4GB flag on a copy of the exe, so the patcher can be checked without touching the real install
+pub fn self_test(exe_path: &Path) -> Result<()> {
+	let _span = tracing::info_span!("self_test").entered();
+	let copy = TempCopy(
+		std::env::temp_dir().join(format!("msu_launcher_self_test_{}.exe", std::process::id())),
+	);
+	std::fs::copy(exe_path, &copy.0)
+		.with_context(|| format!("Couldn't copy {} to test on", exe_path.display()))?;
+	tracing::info!("Self-test: copied the exe to {}", copy.0.display());
+
+	match detect_edition(&copy.0)? {
+		Some(edition) => tracing::info!("Self-test: detected the {} version", edition),
+		None => tracing::info!("Self-test: unknown or already patched version"),
+	}
+	let was_laa = is_laa(&copy.0)?;
+	tracing::info!(
+		"Self-test: 4GB flag is {}",
+		if was_laa { "set" } else { "not set" }
+	);
+
+	remove_laa(&copy.0).context("Failed to clear the 4GB flag")?;
+	expect_laa(&copy.0, false)?;
+	tracing::info!("Self-test: cleared the 4GB flag");
+	make_laa(&copy.0).context("Failed to set the 4GB flag")?;
+	expect_laa(&copy.0, true)?;
+	tracing::info!("Self-test: set the 4GB flag");
+	Ok(())
+}
+
+// Steam can re-verify the exe while it's open, undoing the patch, so warn once before going ahead
+fn check_steam_running(mut config: SyncSignal<Config>, exe_path: &Path) -> Result<()> {
+	if config.read().was_steam_running_warned() {
+		config.with_mut(|c| c.set_steam_running_warned(false));
+		return Ok(());
+	}
+	if is_steam_exe(exe_path)? && process::is_steam_running() {
+		config.with_mut(|c| c.set_steam_running_warned(true));
+		return Err(anyhow!(
+			"Steam is running and may undo the patch, close Steam first or run the 4GB Patcher again to patch anyway"
+		));
+	}
+	Ok(())
+}
+
+// a Steam update replaces the exe, and the 4GB patch along with it
+fn was_updated_since_patch(exe_path: &Path, last_patched: Option<DateTime<Utc>>) -> Result<bool> {
+	let Some(last_patched) = last_patched else {
+		return Ok(false);
+	};
+	if is_laa(exe_path)? {
+		return Ok(false);
+	}
+	let modified: DateTime<Utc> = std::fs::metadata(exe_path)?.modified()?.into();
+	Ok(modified > last_patched)
+}
+
+// like check_steam_running, warns once and lets the next launch go ahead without the patch
+pub fn check_exe_updated(mut config: SyncSignal<Config>) -> Result<()> {
+	if config.read().was_exe_update_warned() {
+		config.with_mut(|c| c.set_exe_update_warned(false));
+		return Ok(());
+	}
+	let Some(exe_path) = config.read().get_launch_exe_path() else {
+		return Ok(());
+	};
+	let last_patched = config.read().get_last_patched();
+	match was_updated_since_patch(exe_path.as_ref(), last_patched) {
+		Ok(true) => {
+			config.with_mut(|c| c.set_exe_update_warned(true));
+			Err(anyhow!(
+				"BattleBrothers.exe was updated since it was last patched, run the 4GB Patcher again or launch again to play without it"
+			))
+		}
+		Ok(false) => Ok(()),
+		Err(e) => {
+			tracing::warn!(
+				"Couldn't check whether the 4GB Patch is still applied: {}",
+				e
+			);
+			Ok(())
+		}
+	}
+}
+
+// uses the backup and launch settings, the last patched time is only recorded for the configured exe
+fn patch_with_settings(
+	mut config: SyncSignal<Config>,
+	exe_path: &Path,
+	is_configured_exe: bool,
+) -> Result<()> {
+	let backup_dir = config.read().get_backup_dir().map(Path::to_path_buf);
+	let make_backups = config.read().make_backups();
+	let keep_original = config.read().launch_unpacked_exe();
+	let steamless_path = config.read().get_steamless_path().to_path_buf();
+	if let Err(e) = check_steam_running(config, exe_path) {
+		tracing::error!("{}", e);
+		return Err(e);
+	}
+	match patch_exe(
+		exe_path,
+		backup_dir.as_deref(),
+		make_backups,
+		keep_original,
+		&steamless_path,
+	) {
+		Ok(outcome) => {
+			if make_backups || outcome == PatchOutcome::AlreadyPatched {
+				tracing::info!("{}", outcome);
+			} else {
+				tracing::warn!(
+					"{} without a backup, backups are disabled in the settings",
+					outcome
+				);
+			}
+			if is_configured_exe {
+				if let Err(e) = config.with_mut(|c| c.set_last_patched()) {
+					tracing::error!("Couldn't save config: {}", e);
+				}
+			}
+			Ok(())
+		}
+		Err(e) => {
+			// the cause, e.g. Steamless missing, matters more than which step failed
+			tracing::error!("{:#}", e);
+			Err(e)
+		}
+	}
+}
+
+pub fn patch_from_config(config: SyncSignal<Config>) -> Result<()> {
 	let exe_path = match config.read().get_bb_exe_path() {
 		Some(path) => path,
 		None => {
@@ -191,14 +760,245 @@ pub fn patch_from_config(config: ReadOnlySignal<Config, SyncStorage>) -> Result<
 			return Err(anyhow!(error));
 		}
 	};
-	match patch_exe(exe_path.as_ref()) {
-		Ok(msg) => {
-			tracing::info!("{}", msg);
-			Ok(())
+	patch_with_settings(config, exe_path.as_ref(), true)
+}
+
+// for a copy of the exe or a second install, the configured game path is left alone
+pub fn patch_chosen_exe(config: SyncSignal<Config>, exe_path: &Path) -> Result<()> {
+	patch_with_settings(config, exe_path, false)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PatchStatus {
+	pub is_laa: bool,
+	pub edition: Option<Edition>,
+	pub architecture: Architecture,
+}
+
+impl std::fmt::Display for PatchStatus {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		if self.architecture == Architecture::X64 {
+			return f.write_str("64-bit build, the 4GB Patch isn't applicable");
 		}
-		Err(e) => {
-			tracing::error!("{}", e.to_string());
-			Err(e)
+		match (self.is_laa, self.edition) {
+			(true, _) => f.write_str("4GB Patch is applied"),
+			(false, Some(edition)) => write!(f, "4GB Patch isn't applied ({} version)", edition),
+			(false, None) => f.write_str("4GB Patch isn't applied (unknown version)"),
 		}
 	}
 }
+
+// only ever opens the exe for reading, so unlike patch_from_config it's safe to run at any time
+pub fn is_patched_from_config(config: ReadOnlySignal<Config, SyncStorage>) -> Result<PatchStatus> {
+	let exe_path = config
+		.read()
+		.get_launch_exe_path()
+		.context("Couldn't find BattleBrothers.exe")?;
+	patch_status(exe_path.as_ref())
+}
+
+pub fn patch_status(exe_path: &Path) -> Result<PatchStatus> {
+	Ok(PatchStatus {
+		is_laa: is_laa(exe_path)?,
+		edition: detect_edition(exe_path)?,
+		architecture: detect_architecture(exe_path)?,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::temp_dir::TempDir;
+
+	// just enough of a PE file for the header to be found
+	fn write_minimal_pe(path: &Path) {
+		let pe_offset = size_of::<IMAGE_DOS_HEADER>();
+		let mut bytes = vec![0u8; pe_offset + 4 + size_of::<IMAGE_FILE_HEADER>()];
+		bytes[0..2].copy_from_slice(b"MZ");
+		// e_lfanew is the last field of IMAGE_DOS_HEADER
+		bytes[pe_offset - 4..pe_offset].copy_from_slice(&(pe_offset as u32).to_le_bytes());
+		bytes[pe_offset..pe_offset + 4].copy_from_slice(b"PE\0\0");
+		std::fs::write(path, bytes).unwrap();
+	}
+
+	#[test]
+	fn laa_flag_toggles() {
+		let dir = TempDir::new("laa_flag");
+		let exe_path = dir.join("BattleBrothers.exe");
+		write_minimal_pe(&exe_path);
+		assert!(!is_laa(&exe_path).unwrap());
+		make_laa(&exe_path).unwrap();
+		assert!(is_laa(&exe_path).unwrap());
+		remove_laa(&exe_path).unwrap();
+		assert!(!is_laa(&exe_path).unwrap());
+
+		self_test(&exe_path).unwrap();
+		assert!(!is_laa(&exe_path).unwrap());
+	}
+
+	#[test]
+	fn architecture_is_detected() {
+		let dir = TempDir::new("architecture");
+		let exe_path = dir.join("BattleBrothers.exe");
+		write_minimal_pe(&exe_path);
+		let mut file = File::options()
+			.read(true)
+			.write(true)
+			.open(&exe_path)
+			.unwrap();
+		seek_to_pe_header(&mut file).unwrap();
+		let mut header = read_image_file_header(&mut file).unwrap();
+		for (machine, expected) in [
+			(IMAGE_FILE_MACHINE_I386, Architecture::X86),
+			(IMAGE_FILE_MACHINE_AMD64, Architecture::X64),
+		] {
+			header.Machine = machine;
+			seek_to_pe_header(&mut file).unwrap();
+			write_image_file_header(&mut file, &header).unwrap();
+			assert_eq!(detect_architecture(&exe_path).unwrap(), expected);
+		}
+		drop(file);
+	}
+
+	#[test]
+	fn steam_stub_section_is_detected() {
+		let dir = TempDir::new("steam_stub");
+		let exe_path = dir.join("BattleBrothers.exe");
+		write_minimal_pe(&exe_path);
+		assert!(!has_steam_drm(&exe_path).unwrap());
+
+		let mut file = File::options()
+			.read(true)
+			.write(true)
+			.open(&exe_path)
+			.unwrap();
+		seek_to_pe_header(&mut file).unwrap();
+		let mut header = read_image_file_header(&mut file).unwrap();
+		header.NumberOfSections = 2;
+		seek_to_pe_header(&mut file).unwrap();
+		write_image_file_header(&mut file, &header).unwrap();
+		file.seek(SeekFrom::End(0)).unwrap();
+		for name in [b".text\0\0\0", STEAM_STUB_SECTION] {
+			let mut section = vec![0u8; size_of::<IMAGE_SECTION_HEADER>()];
+			section[..8].copy_from_slice(name);
+			file.write_all(&section).unwrap();
+		}
+		drop(file);
+		assert!(has_steam_drm(&exe_path).unwrap());
+		assert!(is_steam_exe(&exe_path).unwrap());
+	}
+
+	#[test]
+	fn unknown_exe_reports_its_hash() {
+		let dir = TempDir::new("classify_exe");
+		let exe_path = dir.join("BattleBrothers.exe");
+		write_minimal_pe(&exe_path);
+		let hash = exe_hash_hex(&exe_path).unwrap();
+		assert_eq!(
+			classify_exe(&exe_path).unwrap(),
+			ExeClassification::Unknown { hash: hash.clone() }
+		);
+		let error = patch_exe(&exe_path, None, true, false, Path::new("steamless"))
+			.unwrap_err()
+			.to_string();
+		assert!(error.contains(&hash));
+
+		make_laa(&exe_path).unwrap();
+		assert_eq!(
+			patch_exe(&exe_path, None, true, false, Path::new("steamless")).unwrap(),
+			PatchOutcome::AlreadyPatched
+		);
+	}
+
+	#[test]
+	fn partially_patched_exe_has_matching_backup() {
+		let dir = TempDir::new("partial_patch");
+		let exe_path = dir.join("BattleBrothers.exe");
+		std::fs::write(&exe_path, b"unpacked").unwrap();
+		assert!(!has_matching_backup(&exe_path, "steamless_backup", None).unwrap());
+
+		// the state left behind by a run that failed between DRM removal and the LAA patch
+		make_backup(&exe_path, "steamless_backup", None).unwrap();
+		assert!(has_matching_backup(&exe_path, "steamless_backup", None).unwrap());
+		assert!(!has_matching_backup(&exe_path, "steam_backup", None).unwrap());
+
+		std::fs::write(&exe_path, b"changed since").unwrap();
+		assert!(!has_matching_backup(&exe_path, "steamless_backup", None).unwrap());
+	}
+
+	#[test]
+	fn backups_in_backup_dir_are_found() {
+		let dir = TempDir::new("backup_dir");
+		let backup_dir = dir.join("backups");
+		let exe_path = dir.join("BattleBrothers.exe");
+		std::fs::write(&exe_path, b"unpacked").unwrap();
+		make_backup(&exe_path, "steamless_backup", Some(&backup_dir)).unwrap();
+		assert!(has_matching_backup(&exe_path, "steamless_backup", Some(&backup_dir)).unwrap());
+	}
+
+	#[test]
+	fn updated_exe_is_detected() {
+		let dir = TempDir::new("updated_exe");
+		let exe_path = dir.join("BattleBrothers.exe");
+		write_minimal_pe(&exe_path);
+		let before = Utc::now() - chrono::Duration::hours(1);
+		let after = Utc::now() + chrono::Duration::hours(1);
+		assert!(!was_updated_since_patch(&exe_path, None).unwrap());
+		assert!(!was_updated_since_patch(&exe_path, Some(after)).unwrap());
+		assert!(was_updated_since_patch(&exe_path, Some(before)).unwrap());
+		make_laa(&exe_path).unwrap();
+		assert!(!was_updated_since_patch(&exe_path, Some(before)).unwrap());
+	}
+
+	#[test]
+	fn steam_backup_requires_pristine_exe() {
+		let dir = TempDir::new("steam_backup");
+		let exe_path = dir.join("BattleBrothers.exe");
+		std::fs::write(&exe_path, b"already unpacked").unwrap();
+		assert!(make_backup(&exe_path, "steam_backup", None).is_err());
+		assert!(list_backups(&exe_path, None).unwrap().is_empty());
+	}
+
+	#[test]
+	fn backups_are_never_overwritten() {
+		let dir = TempDir::new("numbered_backups");
+		let exe_path = dir.join("BattleBrothers.exe");
+		std::fs::write(&exe_path, b"first").unwrap();
+		make_backup(&exe_path, "gog_backup", None).unwrap();
+		std::fs::write(&exe_path, b"second").unwrap();
+		make_backup(&exe_path, "gog_backup", None).unwrap();
+
+		let backups = list_backups(&exe_path, None).unwrap();
+		assert_eq!(backups.len(), 2);
+		assert_eq!(
+			std::fs::read(dir.join("BattleBrothers.exe.gog_backup")).unwrap(),
+			b"first"
+		);
+		assert_eq!(
+			std::fs::read(dir.join("BattleBrothers.exe.1.gog_backup")).unwrap(),
+			b"second"
+		);
+	}
+
+	#[test]
+	fn restore_original_backup() {
+		let dir = TempDir::new("restore_backup");
+		let backup_dir = dir.join("backups");
+		let exe_path = dir.join("BattleBrothers.exe");
+		assert!(restore_backup(&exe_path, Some(&backup_dir))
+			.unwrap_err()
+			.to_string()
+			.starts_with("No backup"));
+
+		std::fs::write(&exe_path, b"original").unwrap();
+		make_backup(&exe_path, "gog_backup", None).unwrap();
+		// the unpacked exe is never what gets restored
+		std::fs::write(&exe_path, b"unpacked").unwrap();
+		make_backup(&exe_path, "steamless_backup", Some(&backup_dir)).unwrap();
+		std::fs::write(&exe_path, b"patched").unwrap();
+
+		let backup_path = restore_backup(&exe_path, Some(&backup_dir)).unwrap();
+		assert_eq!(backup_path, dir.join("BattleBrothers.exe.gog_backup"));
+		assert_eq!(std::fs::read(&exe_path).unwrap(), b"original");
+	}
+}
@@ -1,17 +1,54 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use dioxus::signals::{ReadOnlySignal, Readable, SyncStorage};
-use std::collections::HashSet;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
 use std::io::Write;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
 use std::{fs::File, io::Read, path::Path};
 use zip::ZipArchive;
 use zip::{write::SimpleFileOptions, CompressionMethod};
 
-use crate::config::{Config, DataPath};
+use crate::config::{self, Config, DataPath};
+use crate::log::{self, Operation};
 
 const TABBED_NEWLINE: &str = "\n\t\t\t";
 
 const ON_RUNNING_PATH: &str = "preload/on_running.txt";
 const ON_START_PATH: &str = "preload/on_start.txt";
+const MANIFEST_PATH: &str = "preload/manifest.json";
+const SOURCES_PATH: &str = "preload/sources.txt";
+
+// a point in the game's loading that mods can register resources for
+// adding one only takes an entry in HOOKS, its placeholder in the template and a locale key
+#[derive(Debug, PartialEq, Eq)]
+pub struct Hook {
+	// the key in the manifest, and the name used in reports and the sources file
+	pub name: &'static str,
+	// the text file mods list the hook's resources in, the generated mod writes the same file
+	pub path: &'static str,
+	// replaced with the quoted resource list in mod_msu_launcher.nut
+	pub placeholder: &'static str,
+	// the heading of the hook's resources in the generated mod panel
+	pub title_key: &'static str,
+}
+
+pub static HOOKS: [Hook; 2] = [
+	Hook {
+		name: "on_running",
+		path: ON_RUNNING_PATH,
+		placeholder: "$OnRunning$",
+		title_key: "generated_mod_on_running",
+	},
+	Hook {
+		name: "on_start",
+		path: ON_START_PATH,
+		placeholder: "$OnStart$",
+		title_key: "generated_mod_on_start",
+	},
+];
 
 const MOD_ID: &str = "mod_msu_launcher";
 const ZIP_NAME: &str = "~mod_msu_launcher.zip";
@@ -20,39 +57,180 @@ const MOD_NAMESPACE: &str = "MSULauncher";
 const MOD_STRING: &str = include_str!("../squirrel/mod_msu_launcher.nut");
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+// lower priorities are output first, lines without a prefix end up in the middle
+const DEFAULT_PRIORITY: u32 = 50;
+
+// splits a `10:scripts/foo.nut` line into its priority and resource name
+fn parse_resource_line(line: &str) -> (u32, &str) {
+	if let Some((prefix, name)) = line.split_once(':') {
+		if !prefix.is_empty() && prefix.bytes().all(|b| b.is_ascii_digit()) {
+			if let Ok(priority) = prefix.parse() {
+				return (priority, name);
+			}
+		}
+	}
+	(DEFAULT_PRIORITY, line)
+}
+
+// an alternative to the text files for mods that prefer a single structured file, both are read if present
+// a list of entries per hook name, keys that aren't hooks are ignored
+type PreloadManifest = HashMap<String, serde_json::Value>;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ManifestEntry {
+	// same format as a line of the text files, so `10:scripts/foo.nut` works too
+	Line(String),
+	WithPriority { name: String, priority: Option<u32> },
+}
+
 pub struct ResourceGatherer {
-	pub on_running: HashSet<String>,
-	pub on_start: HashSet<String>,
+	// keyed by Hook::name, with an entry for every hook
+	pub hooks: HashMap<&'static str, HashMap<String, u32>>,
+	// the file names of the mods that listed each resource
+	sources: HashMap<String, Vec<String>>,
 }
 
 impl ResourceGatherer {
 	pub fn new() -> Self {
 		Self {
-			on_running: HashSet::new(),
-			on_start: HashSet::new(),
+			hooks: HOOKS
+				.iter()
+				.map(|hook| (hook.name, HashMap::new()))
+				.collect(),
+			sources: HashMap::new(),
+		}
+	}
+
+	pub fn hook_mut(&mut self, hook: &Hook) -> &mut HashMap<String, u32> {
+		self.hooks.entry(hook.name).or_default()
+	}
+
+	// adds everything gathered from a single mod, recording it as the source of each resource
+	fn add_mod(&mut self, other: ResourceGatherer, source: &str) {
+		for (hook_name, other_resources) in other.hooks {
+			let resources = self.hooks.entry(hook_name).or_default();
+			for (name, priority) in other_resources {
+				let sources = self.sources.entry(name.clone()).or_default();
+				if !sources.iter().any(|existing| existing == source) {
+					sources.push(source.to_owned());
+				}
+				ResourceGatherer::insert(resources, &name, priority);
+			}
+		}
+	}
+
+	// combines two scanned sets, e.g. the data folder and a staging folder, before creating the mod
+	// resources listed in both keep the earliest priority and the sources of both
+	pub fn merge(&mut self, other: ResourceGatherer) {
+		for (hook_name, other_resources) in other.hooks {
+			let resources = self.hooks.entry(hook_name).or_default();
+			for (name, priority) in other_resources {
+				ResourceGatherer::insert(resources, &name, priority);
+			}
+		}
+		for (name, other_sources) in other.sources {
+			let sources = self.sources.entry(name).or_default();
+			for source in other_sources {
+				if !sources.contains(&source) {
+					sources.push(source);
+				}
+			}
+		}
+	}
+
+	// if several mods list the same resource, the earliest priority wins
+	fn insert(resources: &mut HashMap<String, u32>, name: &str, priority: u32) {
+		resources
+			.entry(name.to_owned())
+			.and_modify(|existing| *existing = (*existing).min(priority))
+			.or_insert(priority);
+	}
+
+	fn insert_line(resources: &mut HashMap<String, u32>, line: &str) {
+		let (priority, name) = parse_resource_line(line);
+		ResourceGatherer::insert(resources, name, priority);
+	}
+
+	fn insert_manifest_entries(resources: &mut HashMap<String, u32>, entries: &[ManifestEntry]) {
+		for entry in entries {
+			match entry {
+				ManifestEntry::Line(line) => ResourceGatherer::insert_line(resources, line),
+				ManifestEntry::WithPriority { name, priority } => {
+					ResourceGatherer::insert(resources, name, priority.unwrap_or(DEFAULT_PRIORITY))
+				}
+			}
 		}
 	}
+
+	fn into_sorted(resources: HashMap<String, u32>) -> Vec<String> {
+		let mut resources: Vec<_> = resources.into_iter().collect();
+		resources.sort_by(|(a_name, a_priority), (b_name, b_priority)| {
+			a_priority.cmp(b_priority).then_with(|| a_name.cmp(b_name))
+		});
+		resources.into_iter().map(|(name, _)| name).collect()
+	}
 }
 
 pub struct ResourceHandler {
-	on_running: Vec<String>,
-	on_start: Vec<String>,
+	// sorted by priority, in the order of HOOKS
+	hooks: Vec<(&'static Hook, Vec<String>)>,
+	// only written into the generated mod by the verbose patcher
+	sources: Option<HashMap<String, Vec<String>>>,
 }
 
 impl From<ResourceGatherer> for ResourceHandler {
-	fn from(value: ResourceGatherer) -> Self {
-		let mut on_running: Vec<_> = value.on_running.into_iter().collect();
-		on_running.sort();
-		let mut on_start: Vec<_> = value.on_start.into_iter().collect();
-		on_start.sort();
+	fn from(mut value: ResourceGatherer) -> Self {
 		Self {
-			on_running,
-			on_start,
+			hooks: HOOKS
+				.iter()
+				.map(|hook| {
+					let resources = value.hooks.remove(hook.name).unwrap_or_default();
+					(hook, ResourceGatherer::into_sorted(resources))
+				})
+				.collect(),
+			sources: Some(value.sources),
 		}
 	}
 }
 
 impl ResourceHandler {
+	pub fn without_sources(mut self) -> Self {
+		self.sources = None;
+		self
+	}
+
+	pub fn resources(&self, hook: &Hook) -> &[String] {
+		self.hooks
+			.iter()
+			.find(|(handler_hook, _)| *handler_hook == hook)
+			.map_or(&[], |(_, resources)| resources.as_slice())
+	}
+
+	// every hook with its resources, in the order of HOOKS
+	pub fn hooks(&self) -> impl Iterator<Item = (&'static Hook, &[String])> {
+		self.hooks
+			.iter()
+			.map(|(hook, resources)| (*hook, resources.as_slice()))
+	}
+
+	// one `resource <- mod.zip, other_mod.zip` line per resource, in load order
+	fn make_sources_string(&self) -> Option<String> {
+		let sources = self.sources.as_ref()?;
+		let mut s = String::new();
+		for (hook, resources) in self.hooks() {
+			s.push_str(&format!("# {}\n", hook.name));
+			for name in resources {
+				let mods = sources
+					.get(name)
+					.map(|mods| mods.join(", "))
+					.unwrap_or_default();
+				s.push_str(&format!("{} <- {}\n", name, mods));
+			}
+		}
+		Some(s)
+	}
+
 	fn make_quoted_strings(strings: &[String]) -> String {
 		let mut s = "[".to_owned();
 		if !strings.is_empty() {
@@ -69,12 +247,8 @@ impl ResourceHandler {
 		s
 	}
 
-	pub fn get_on_running_quoted(&self) -> String {
-		ResourceHandler::make_quoted_strings(&self.on_running)
-	}
-
-	pub fn get_on_start_quoted(&self) -> String {
-		ResourceHandler::make_quoted_strings(&self.on_start)
+	pub fn get_quoted(&self, hook: &Hook) -> String {
+		ResourceHandler::make_quoted_strings(self.resources(hook))
 	}
 
 	fn make_raw_strings(strings: &[String]) -> String {
@@ -85,12 +259,8 @@ impl ResourceHandler {
 		s
 	}
 
-	pub fn get_on_running_raw(&self) -> String {
-		ResourceHandler::make_raw_strings(&self.on_running)
-	}
-
-	pub fn get_on_start_raw(&self) -> String {
-		ResourceHandler::make_raw_strings(&self.on_start)
+	pub fn get_raw(&self, hook: &Hook) -> String {
+		ResourceHandler::make_raw_strings(self.resources(hook))
 	}
 }
 
@@ -105,42 +275,454 @@ fn read_file_in_zip(zip_file: &mut ZipArchive<File>, name: &str) -> Result<Strin
 	Ok(contents)
 }
 
-pub fn gather_resources_for_mod(gatherer: &mut ResourceGatherer, mod_path: &Path) -> Result<()> {
+fn get_preload_path() -> String {
+	format!("scripts/!mods_preload/{}.nut", MOD_ID)
+}
+
+// checked by content rather than file name, so a renamed copy of the generated mod isn't gathered from
+fn is_generated_mod(zip_file: &mut ZipArchive<File>) -> Result<bool> {
+	let preload = read_file_in_zip(zip_file, &get_preload_path())?;
+	Ok(preload.contains(&format!("ID = \"{}\"", MOD_ID)))
+}
+
+// what is at the generated mod's path before the patcher writes it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExistingMod {
+	#[default]
+	Missing,
+	// written by this launcher, recognized by MOD_ID
+	Generated,
+	// hand-made or from another tool, its contents are lost when the patcher replaces it
+	Foreign,
+}
+
+pub fn existing_mod(data_path: &DataPath) -> ExistingMod {
+	let path = data_path.join(ZIP_NAME);
+	if !path.exists() {
+		return ExistingMod::Missing;
+	}
+	let generated = File::open(&path)
+		.ok()
+		.and_then(|file| ZipArchive::new(file).ok())
+		.is_some_and(|mut zip_file| is_generated_mod(&mut zip_file).unwrap_or(false));
+	if generated {
+		ExistingMod::Generated
+	} else {
+		ExistingMod::Foreign
+	}
+}
+
+// None for zips that aren't mods at all and for previous patcher output
+fn read_mod_resources(mod_path: &Path) -> Result<Option<ResourceGatherer>> {
 	let file = std::fs::File::open(mod_path)?;
 	// not sure why the API requires this to be mut
 	let mut zip_file = match zip::ZipArchive::new(file) {
-		Err(zip::result::ZipError::InvalidArchive(_)) => return Ok(()),
+		Err(zip::result::ZipError::InvalidArchive(_)) => return Ok(None),
+		Err(e) => return Err(anyhow!(e)),
+		Ok(zip) => zip,
+	};
+	if is_generated_mod(&mut zip_file)? {
+		tracing::info!("Ignoring previous patcher output {}", mod_path.display());
+		return Ok(None);
+	}
+	// gathered separately first, so a mod that fails halfway through contributes nothing
+	let mut mod_gatherer = ResourceGatherer::new();
+	for hook in &HOOKS {
+		for line in read_file_in_zip(&mut zip_file, hook.path)?.lines() {
+			ResourceGatherer::insert_line(mod_gatherer.hook_mut(hook), line);
+		}
+	}
+	let manifest = read_file_in_zip(&mut zip_file, MANIFEST_PATH)?;
+	if !manifest.is_empty() {
+		let mut manifest: PreloadManifest = serde_json::from_str(&manifest)
+			.with_context(|| format!("Invalid {}", MANIFEST_PATH))?;
+		for hook in &HOOKS {
+			let Some(entries) = manifest.remove(hook.name) else {
+				continue;
+			};
+			let entries: Vec<ManifestEntry> = serde_json::from_value(entries)
+				.with_context(|| format!("Invalid {} in {}", hook.name, MANIFEST_PATH))?;
+			ResourceGatherer::insert_manifest_entries(mod_gatherer.hook_mut(hook), &entries);
+		}
+	}
+	// a name that can't be quoted would break the generated mod for every mod, so only skip this one
+	for (hook_name, resources) in &mod_gatherer.hooks {
+		for name in resources.keys() {
+			check_resource_name(name).with_context(|| format!("Invalid {} resource", hook_name))?;
+		}
+	}
+	Ok(Some(mod_gatherer))
+}
+
+// the names are written into the generated mod as quoted strings without any escaping
+fn check_resource_name(name: &str) -> Result<()> {
+	match name.chars().find(|c| matches!(c, '"' | '\\' | '\n' | '\r')) {
+		Some(c) => Err(anyhow!("{:?} can't contain {:?}", name, c)),
+		None => Ok(()),
+	}
+}
+
+const PRELOAD_FOLDER: &str = "scripts/!mods_preload/";
+// the ways mods register themselves, the first three arguments are always ID, version and name
+const REGISTER_CALLS: [&str; 3] = [
+	"::Hooks.register(",
+	"::mods_registerMod(",
+	"::MSU.Class.Mod(",
+];
+// the first argument of MSU's Registry.addModSource, followed by the repository URL
+const GITHUB_SOURCE: &str = "ModSourceDomain.GitHub,";
+
+// whatever could be read from a mod's preload scripts, BB itself doesn't require any of it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModMetadata {
+	pub id: Option<String>,
+	pub name: Option<String>,
+	pub version: Option<String>,
+	// the GitHub repository an MSU mod is released from, used to check it for updates
+	pub update_source: Option<String>,
+}
+
+impl ModMetadata {
+	fn is_empty(&self) -> bool {
+		self.id.is_none() && self.name.is_none()
+	}
+
+	fn or(self, other: ModMetadata) -> ModMetadata {
+		ModMetadata {
+			id: self.id.or(other.id),
+			name: self.name.or(other.name),
+			version: self.version.or(other.version),
+			update_source: self.update_source.or(other.update_source),
+		}
+	}
+}
+
+// a string or number literal at the start of s, None for anything else like a variable
+fn parse_literal(s: &str) -> Option<String> {
+	if let Some(rest) = s.strip_prefix('"') {
+		let end = rest.find('"')?;
+		return Some(rest[..end].to_owned());
+	}
+	let end = s
+		.find(|c: char| !(c.is_ascii_digit() || c == '.'))
+		.unwrap_or(s.len());
+	(end > 0).then(|| s[..end].to_owned())
+}
+
+// e.g. `Version = "1.2.0",` in the table most mods keep their ID, Name and Version in
+fn find_table_field(script: &str, key: &str) -> Option<String> {
+	script.lines().find_map(|line| {
+		let value = line
+			.trim()
+			.strip_prefix(key)?
+			.trim_start()
+			.strip_prefix('=')?;
+		parse_literal(value.trim())
+	})
+}
+
+fn find_register_call(script: &str) -> ModMetadata {
+	REGISTER_CALLS
+		.iter()
+		.find_map(|call| {
+			let args = &script[script.find(call)? + call.len()..];
+			let mut args = args[..args.find(')')?]
+				.split(',')
+				.map(|arg| parse_literal(arg.trim()));
+			Some(ModMetadata {
+				id: args.next().flatten(),
+				version: args.next().flatten(),
+				name: args.next().flatten(),
+				update_source: None,
+			})
+		})
+		.unwrap_or_default()
+}
+
+fn parse_mod_metadata(script: &str) -> ModMetadata {
+	// register calls usually pass the table fields rather than literals
+	find_register_call(script).or(ModMetadata {
+		id: find_table_field(script, "ID"),
+		name: find_table_field(script, "Name"),
+		version: find_table_field(script, "Version"),
+		update_source: find_github_source(script),
+	})
+}
+
+// e.g. `::ModA.Mod.Registry.addModSource(::MSU.System.Registry.ModSourceDomain.GitHub, "https://github.com/...")`
+fn find_github_source(script: &str) -> Option<String> {
+	let start = script.find(GITHUB_SOURCE)? + GITHUB_SOURCE.len();
+	parse_literal(script[start..].trim_start())
+}
+
+// None for zips that aren't mods and mods whose preload scripts don't say what they are
+pub fn read_mod_metadata(mod_path: &Path) -> Result<Option<ModMetadata>> {
+	let file = std::fs::File::open(mod_path)?;
+	let mut zip_file = match zip::ZipArchive::new(file) {
+		Err(zip::result::ZipError::InvalidArchive(_)) => return Ok(None),
 		Err(e) => return Err(anyhow!(e)),
 		Ok(zip) => zip,
 	};
-	for line in read_file_in_zip(&mut zip_file, ON_RUNNING_PATH)?.lines() {
-		gatherer.on_running.insert(line.to_owned());
+	let mut scripts: Vec<String> = zip_file
+		.file_names()
+		.filter(|name| name.starts_with(PRELOAD_FOLDER) && name.ends_with(".nut"))
+		.map(ToOwned::to_owned)
+		.collect();
+	scripts.sort();
+	for script in scripts {
+		let metadata = parse_mod_metadata(&read_file_in_zip(&mut zip_file, &script)?);
+		if !metadata.is_empty() {
+			return Ok(Some(metadata));
+		}
 	}
-	for line in read_file_in_zip(&mut zip_file, ON_START_PATH)?.lines() {
-		gatherer.on_start.insert(line.to_owned());
+	Ok(None)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModInfo {
+	pub file_name: String,
+	pub metadata: Option<ModMetadata>,
+}
+
+// a mod that can't be read is still listed, just without its metadata
+pub fn list_mods_with_metadata(mods_path: &DataPath) -> Result<Vec<ModInfo>> {
+	Ok(list_mods(mods_path)?
+		.into_iter()
+		.map(|file_name| {
+			let metadata =
+				read_mod_metadata(&mods_path.as_ref().join(&file_name)).unwrap_or_else(|e| {
+					tracing::debug!("Couldn't read metadata of {}: {:#}", file_name, e);
+					None
+				});
+			ModInfo {
+				file_name,
+				metadata,
+			}
+		})
+		.collect())
+}
+
+fn mod_file_name(mod_path: &Path) -> String {
+	mod_path
+		.file_name()
+		.map(|name| name.to_string_lossy().into_owned())
+		.unwrap_or_else(|| mod_path.to_string_lossy().into_owned())
+}
+
+pub fn gather_resources_for_mod(gatherer: &mut ResourceGatherer, mod_path: &Path) -> Result<()> {
+	if let Some(mod_gatherer) = read_mod_resources(mod_path)? {
+		gatherer.add_mod(mod_gatherer, &mod_file_name(mod_path));
 	}
 	Ok(())
 }
 
-pub fn get_resource_handler(data_path: &DataPath) -> Result<ResourceHandler> {
-	let entries: Result<Vec<_>, _> = std::fs::read_dir(data_path)?.collect();
-	let entries = entries?;
-	let mut gatherer = ResourceGatherer::new();
-	for e in entries.into_iter() {
+// each thread takes the next unread mod, results keep the order of mod_paths
+// a cancel stops the threads after the mod they're reading, before anything is written
+fn read_mods_in_parallel(
+	mod_paths: &[PathBuf],
+	operation: Option<&Operation>,
+	threads: usize,
+) -> Result<Vec<Result<Option<ResourceGatherer>>>> {
+	let next = AtomicUsize::new(0);
+	let done = AtomicUsize::new(0);
+	let results: Mutex<Vec<_>> = Mutex::new(mod_paths.iter().map(|_| None).collect());
+	std::thread::scope(|scope| {
+		for _ in 0..threads.clamp(1, mod_paths.len().max(1)) {
+			scope.spawn(|| loop {
+				if operation.is_some_and(|operation| operation.check_cancelled().is_err()) {
+					break;
+				}
+				let i = next.fetch_add(1, Ordering::Relaxed);
+				let Some(path) = mod_paths.get(i) else {
+					break;
+				};
+				let result = read_mod_resources(path);
+				results.lock().unwrap()[i] = Some(result);
+				let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+				if let Some(operation) = operation {
+					operation.set_progress(done as f32 / mod_paths.len() as f32);
+				}
+			});
+		}
+	});
+	if let Some(operation) = operation {
+		operation.check_cancelled()?;
+	}
+	// only a cancel makes the threads stop before every mod is read
+	results
+		.into_inner()
+		.unwrap()
+		.into_iter()
+		.collect::<Option<Vec<_>>>()
+		.ok_or_else(|| log::Cancelled.into())
+}
+
+pub struct SkippedMod {
+	pub path: PathBuf,
+	pub error: anyhow::Error,
+}
+
+impl SkippedMod {
+	pub fn name(&self) -> String {
+		self.path
+			.file_name()
+			.map(|name| name.to_string_lossy().into_owned())
+			.unwrap_or_else(|| self.path.display().to_string())
+	}
+}
+
+#[derive(Default)]
+pub struct PatchSummary {
+	pub mods_scanned: usize,
+	pub mods_skipped: Vec<SkippedMod>,
+	// the number of resources registered for each hook, in the order of HOOKS
+	pub resource_counts: Vec<(&'static str, usize)>,
+	// None when there was no previous generated mod to compare against
+	pub changes: Option<ResourceChanges>,
+	// false when the previous generated mod was already identical and left alone
+	pub rewritten: bool,
+	pub existing: ExistingMod,
+}
+
+impl PatchSummary {
+	pub fn has_resources(&self) -> bool {
+		self.resource_counts.iter().any(|(_, count)| *count > 0)
+	}
+
+	// e.g. "5 on_running and 3 on_start resources"
+	pub fn describe_counts(&self) -> String {
+		let counts: Vec<_> = self
+			.resource_counts
+			.iter()
+			.map(|(name, count)| format!("{} {}", count, name))
+			.collect();
+		format!("{} resources", counts.join(" and "))
+	}
+
+	// what running the patcher would change, for a preview
+	pub fn describe_pending_changes(&self) -> String {
+		if self.existing == ExistingMod::Foreign {
+			return format!(
+				"would replace {}, which wasn't made by the launcher",
+				ZIP_NAME
+			);
+		}
+		match (&self.changes, self.rewritten) {
+			(None, _) => "new preload mod".to_string(),
+			(Some(_), false) => "nothing would change".to_string(),
+			(Some(changes), true) => changes.to_string(),
+		}
+	}
+
+	// so re-running the patcher shows whether it actually did anything
+	pub fn describe_changes(&self) -> String {
+		// the resource counts of a foreign file say nothing about what was lost
+		if self.existing == ExistingMod::Foreign && self.rewritten {
+			return format!("replaced {}, which wasn't made by the launcher", ZIP_NAME);
+		}
+		match (&self.changes, self.rewritten) {
+			(None, _) => "new preload mod".to_string(),
+			(Some(_), false) => "nothing changed, kept the existing preload mod".to_string(),
+			(Some(changes), true) => changes.to_string(),
+		}
+	}
+}
+
+fn scan_threads() -> usize {
+	std::thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+// a single broken mod (locked, partially downloaded...) shouldn't prevent generating the patcher for all the others
+pub fn get_resource_handler(
+	mods_path: &DataPath,
+	staging_path: Option<&DataPath>,
+	excluded_mods: &[String],
+	operation: Option<&Operation>,
+) -> Result<(ResourceHandler, PatchSummary)> {
+	gather_resources(
+		mods_path,
+		staging_path,
+		excluded_mods,
+		operation,
+		scan_threads(),
+	)
+}
+
+// the staging folder's mods are scanned separately and merged in, as if they were already installed
+fn gather_resources(
+	mods_path: &DataPath,
+	staging_path: Option<&DataPath>,
+	excluded_mods: &[String],
+	operation: Option<&Operation>,
+	threads: usize,
+) -> Result<(ResourceHandler, PatchSummary)> {
+	let (mut gatherer, mut summary) = gather_mods(mods_path, excluded_mods, operation, threads)?;
+	if let Some(staging_path) = staging_path {
+		let (staging, staging_summary) =
+			gather_mods(staging_path, excluded_mods, operation, threads)?;
+		gatherer.merge(staging);
+		summary.mods_scanned += staging_summary.mods_scanned;
+		summary.mods_skipped.extend(staging_summary.mods_skipped);
+	}
+	let handler = ResourceHandler::from(gatherer);
+	summary.resource_counts = handler
+		.hooks()
+		.map(|(hook, resources)| (hook.name, resources.len()))
+		.collect();
+	Ok((handler, summary))
+}
+
+// mods are read in parallel but merged in file name order, so the output doesn't depend on the thread count
+fn gather_mods(
+	mods_path: &DataPath,
+	excluded_mods: &[String],
+	operation: Option<&Operation>,
+	threads: usize,
+) -> Result<(ResourceGatherer, PatchSummary)> {
+	let entries: Result<Vec<_>, _> = std::fs::read_dir(mods_path)?.collect();
+	let mut mod_paths = Vec::new();
+	for e in entries? {
 		if let Ok(file_type) = e.file_type() {
-			if file_type.is_dir() || e.file_name().to_string_lossy().ends_with(ZIP_NAME) {
+			// compared as OsStr, a lossy conversion could make an oddly named mod match ZIP_NAME
+			if file_type.is_dir() || e.file_name() == ZIP_NAME {
 				continue;
 			}
-			gather_resources_for_mod(&mut gatherer, &e.path())?;
+			let path = e.path();
+			if excluded_mods
+				.iter()
+				.any(|name| e.file_name() == name.as_str())
+			{
+				tracing::info!("Excluding mod {}", path.display());
+				continue;
+			}
+			mod_paths.push(path);
+		}
+	}
+	mod_paths.sort();
+	let results = read_mods_in_parallel(&mod_paths, operation, threads)?;
+	let mut gatherer = ResourceGatherer::new();
+	let mut summary = PatchSummary {
+		mods_scanned: mod_paths.len(),
+		..Default::default()
+	};
+	for (path, result) in mod_paths.into_iter().zip(results) {
+		match result {
+			Ok(Some(mod_gatherer)) => gatherer.add_mod(mod_gatherer, &mod_file_name(&path)),
+			Ok(None) => {}
+			Err(error) => {
+				tracing::warn!("Skipping mod {}: {:#}", path.display(), error);
+				summary.mods_skipped.push(SkippedMod { path, error });
+			}
 		}
 	}
-	Ok(gatherer.into())
+	Ok((gatherer, summary))
 }
 
 fn get_mod_string(resource_handler: &ResourceHandler) -> String {
-	let mod_string = MOD_STRING.to_owned();
-	let mod_string = mod_string.replace("$OnRunning$", &resource_handler.get_on_running_quoted());
-	let mod_string = mod_string.replace("$OnStart$", &resource_handler.get_on_start_quoted());
+	let mut mod_string = MOD_STRING.to_owned();
+	for hook in &HOOKS {
+		mod_string = mod_string.replace(hook.placeholder, &resource_handler.get_quoted(hook));
+	}
 	let mod_string = mod_string.replace("$Version$", &format!("\"{}\"", VERSION));
 	let mod_string = mod_string.replace("$Name$", &format!("\"{}\"", MOD_NAME));
 	let mod_string = mod_string.replace("$ID$", &format!("\"{}\"", MOD_ID));
@@ -148,28 +730,412 @@ fn get_mod_string(resource_handler: &ResourceHandler) -> String {
 	mod_string.replace("$NameSpace$", MOD_NAMESPACE)
 }
 
+// not a real parser, just enough to catch a broken template
+// before it ends up in a mod that crashes BB on startup
+fn validate_squirrel(mod_string: &str) -> Result<()> {
+	let mut brackets = Vec::new();
+	let mut chars = mod_string.chars();
+	while let Some(c) = chars.next() {
+		match c {
+			'"' => loop {
+				match chars.next() {
+					Some('"') => break,
+					Some('\\') => {
+						chars.next();
+					}
+					Some('\n') | None => {
+						return Err(anyhow!("Unterminated string in generated mod"))
+					}
+					Some(_) => {}
+				}
+			},
+			'(' | '[' | '{' => brackets.push(c),
+			')' | ']' | '}' => {
+				let expected = match c {
+					')' => '(',
+					']' => '[',
+					_ => '{',
+				};
+				if brackets.pop() != Some(expected) {
+					return Err(anyhow!("Unbalanced '{}' in generated mod", c));
+				}
+			}
+			_ => {}
+		}
+	}
+	match brackets.last() {
+		Some(c) => Err(anyhow!("Unclosed '{}' in generated mod", c)),
+		None => Ok(()),
+	}
+}
+
+// set when the mods the patcher would include change, e.g. a mod is excluded,
+// the generated mod keeps loading the old resources until it's recreated
+static PRELOAD_STALE: AtomicBool = AtomicBool::new(false);
+
+// shown until the preload mod is recreated, reason is e.g. "Excluded mods changed"
+pub fn mark_preload_stale(reason: &str) {
+	let message = format!("{}, run Preload Patcher to update the preload mod", reason);
+	tracing::info!("{}", message);
+	PRELOAD_STALE.store(true, Ordering::Relaxed);
+	log::set_notice(Some(message));
+}
+
 pub fn create_mod(data_path: &DataPath, resources: &ResourceHandler) -> Result<()> {
-	let mut zip = zip::ZipWriter::new(std::fs::File::create(data_path.join(ZIP_NAME))?);
-	let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+	create_mod_at(&data_path.join(ZIP_NAME), resources)?;
+	if PRELOAD_STALE.swap(false, Ordering::Relaxed) {
+		log::set_notice(None::<String>);
+	}
+	Ok(())
+}
+
+// BB only loads the mod from the data folder, other paths are for staging or inspecting the output
+pub fn create_mod_at(out_path: &Path, resources: &ResourceHandler) -> Result<()> {
 	let mod_string = get_mod_string(resources);
-	zip.start_file(format!("scripts/!mods_preload/{}.nut", MOD_ID), options)?;
+	validate_squirrel(&mod_string).context("Generated mod failed validation")?;
+
+	let mut zip = zip::ZipWriter::new(
+		std::fs::File::create(out_path)
+			.with_context(|| format!("Couldn't create {}", out_path.display()))?,
+	);
+	let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+	zip.start_file(get_preload_path(), options)?;
 	zip.write_all(mod_string.as_bytes())?;
 
-	zip.start_file(ON_RUNNING_PATH, options)?;
-	zip.write_all(resources.get_on_running_raw().as_bytes())?;
-	zip.start_file(ON_START_PATH, options)?;
-	zip.write_all(resources.get_on_start_raw().as_bytes())?;
+	for hook in &HOOKS {
+		zip.start_file(hook.path, options)?;
+		zip.write_all(resources.get_raw(hook).as_bytes())?;
+	}
+	if let Some(sources) = resources.make_sources_string() {
+		zip.start_file(SOURCES_PATH, options)?;
+		zip.write_all(sources.as_bytes())?;
+	}
+
+	zip.finish()?;
+	Ok(())
+}
+
+#[derive(Clone, PartialEq)]
+pub struct GeneratedMod {
+	pub mod_string: String,
+	// the contents of each hook's resource file, in the order of HOOKS
+	pub hooks: Vec<(&'static Hook, String)>,
+	// empty unless it was written by the verbose patcher
+	pub sources: String,
+}
+
+impl From<&ResourceHandler> for GeneratedMod {
+	fn from(resources: &ResourceHandler) -> Self {
+		Self {
+			mod_string: get_mod_string(resources),
+			hooks: HOOKS
+				.iter()
+				.map(|hook| (hook, resources.get_raw(hook)))
+				.collect(),
+			sources: resources.make_sources_string().unwrap_or_default(),
+		}
+	}
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ResourceChanges {
+	// the resources added and removed for each hook, in the order of HOOKS
+	pub hooks: Vec<(&'static str, usize, usize)>,
+}
+
+impl ResourceChanges {
+	fn count(previous: &str, current: &[String]) -> (usize, usize) {
+		let previous: HashSet<&str> = previous.lines().collect();
+		let current: HashSet<&str> = current.iter().map(String::as_str).collect();
+		(
+			current.difference(&previous).count(),
+			previous.difference(&current).count(),
+		)
+	}
+
+	// a hook the previous mod didn't have yet counts everything as added
+	fn between(previous: &GeneratedMod, resources: &ResourceHandler) -> Self {
+		let hooks = resources
+			.hooks()
+			.map(|(hook, current)| {
+				let previous = previous
+					.hooks
+					.iter()
+					.find(|(previous_hook, _)| *previous_hook == hook)
+					.map_or("", |(_, contents)| contents.as_str());
+				let (added, removed) = ResourceChanges::count(previous, current);
+				(hook.name, added, removed)
+			})
+			.collect();
+		Self { hooks }
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.hooks
+			.iter()
+			.all(|(_, added, removed)| *added == 0 && *removed == 0)
+	}
+}
+
+// e.g. "+3 on_start, -1 on_running", additions first and only the counts that aren't zero
+impl std::fmt::Display for ResourceChanges {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		if self.is_empty() {
+			return f.write_str("no resources added or removed");
+		}
+		let added = self
+			.hooks
+			.iter()
+			.map(|(name, added, _)| ('+', *added, *name));
+		let removed = self
+			.hooks
+			.iter()
+			.map(|(name, _, removed)| ('-', *removed, *name));
+		let parts: Vec<_> = added
+			.chain(removed)
+			.filter(|(_, count, _)| *count > 0)
+			.map(|(sign, count, hook)| format!("{}{} {}", sign, count, hook))
+			.collect();
+		f.write_str(&parts.join(", "))
+	}
+}
+
+// reads back what create_mod last wrote, so users can see exactly which resources were registered
+pub fn read_generated_mod(data_path: &DataPath) -> Result<GeneratedMod> {
+	let zip_path = data_path.join(ZIP_NAME);
+	let file = File::open(&zip_path).with_context(|| {
+		format!(
+			"Couldn't open {}, run the Preload Patcher first",
+			zip_path.display()
+		)
+	})?;
+	let mut zip_file = ZipArchive::new(file)?;
+	let mut hooks = Vec::new();
+	for hook in &HOOKS {
+		hooks.push((hook, read_file_in_zip(&mut zip_file, hook.path)?));
+	}
+	Ok(GeneratedMod {
+		mod_string: read_file_in_zip(&mut zip_file, &get_preload_path())?,
+		hooks,
+		sources: read_file_in_zip(&mut zip_file, SOURCES_PATH)?,
+	})
+}
+
+// the file names of all mod zips in a folder, apart from the one the patcher generates
+pub fn list_mods(mods_path: &DataPath) -> Result<Vec<String>> {
+	let mut mods: Vec<String> = std::fs::read_dir(mods_path)?
+		.flatten()
+		.map(|e| e.path())
+		.filter(|path| {
+			path.is_file()
+				&& path
+					.extension()
+					.is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+		})
+		.filter_map(|path| path.file_name().map(ToOwned::to_owned))
+		.filter(|name| name != ZIP_NAME)
+		.map(|name| name.to_string_lossy().into_owned())
+		.collect();
+	mods.sort();
+	Ok(mods)
+}
+
+// next to the mods folder rather than in it, so BB doesn't pick the archives up
+const MOD_BACKUPS_FOLDER: &str = "mod_backups";
+
+fn mod_backups_path(mods_path: &DataPath) -> PathBuf {
+	let mods_path: &Path = mods_path.as_ref();
+	mods_path
+		.parent()
+		.unwrap_or(mods_path)
+		.join(MOD_BACKUPS_FOLDER)
+}
 
+// a snapshot of every mod zip apart from the generated one, which the Preload Patcher can recreate
+pub fn backup_mods(mods_path: &DataPath) -> Result<PathBuf> {
+	let backups_path = mod_backups_path(mods_path);
+	std::fs::create_dir_all(&backups_path)
+		.with_context(|| format!("Couldn't create {}", backups_path.display()))?;
+	let stem = format!("mods_{}", chrono::Local::now().format("%Y%m%d_%H%M%S"));
+	let mut archive_path = backups_path.join(format!("{}.zip", stem));
+	let mut number = 1;
+	while archive_path.try_exists()? {
+		archive_path = backups_path.join(format!("{}_{}.zip", stem, number));
+		number += 1;
+	}
+	let mut zip = zip::ZipWriter::new(
+		File::create(&archive_path)
+			.with_context(|| format!("Couldn't create {}", archive_path.display()))?,
+	);
+	// the mods are zips already, compressing them again would only be slower
+	let options = SimpleFileOptions::default()
+		.compression_method(CompressionMethod::Stored)
+		.large_file(true);
+	for name in list_mods(mods_path)? {
+		let mod_path = mods_path.join(&name);
+		let mut mod_file = File::open(&mod_path)
+			.with_context(|| format!("Couldn't open {}", mod_path.display()))?;
+		zip.start_file(name.as_str(), options)?;
+		std::io::copy(&mut mod_file, &mut zip)
+			.with_context(|| format!("Couldn't back up {}", name))?;
+	}
 	zip.finish()?;
+	Ok(archive_path)
+}
+
+// puts the backed up mods back, overwriting them if they exist
+// mods added since the backup are left alone, so nothing is lost by restoring the wrong archive
+pub fn restore_mods(mods_path: &DataPath, archive_path: &Path) -> Result<Vec<String>> {
+	let file = File::open(archive_path)
+		.with_context(|| format!("Couldn't open {}", archive_path.display()))?;
+	let mut zip = ZipArchive::new(file)
+		.with_context(|| format!("{} isn't a mods backup", archive_path.display()))?;
+	let mut restored = Vec::new();
+	for i in 0..zip.len() {
+		let mut mod_file = zip.by_index(i)?;
+		// only bare file names, an entry can't be allowed to write outside the mods folder
+		let name = mod_file
+			.enclosed_name()
+			.filter(|name| name.components().count() == 1)
+			.and_then(|name| name.to_str().map(ToOwned::to_owned))
+			.with_context(|| format!("Unexpected file {} in the backup", mod_file.name()))?;
+		let mod_path = mods_path.join(&name);
+		let mut out_file = File::create(&mod_path)
+			.with_context(|| format!("Couldn't write {}", mod_path.display()))?;
+		std::io::copy(&mut mod_file, &mut out_file)
+			.with_context(|| format!("Couldn't restore {}", name))?;
+		restored.push(name);
+	}
+	mark_preload_stale("Restored mods from the backup");
+	Ok(restored)
+}
+
+// BB loads the zips in its data folder in the order Windows lists them, which on NTFS is by uppercased name
+// so `~` sorts after every letter and the generated mod is loaded last
+pub fn load_order(data_path: &DataPath) -> Result<Vec<String>> {
+	let mut mods = list_mods(data_path)?;
+	if data_path.join(ZIP_NAME).is_file() {
+		mods.push(ZIP_NAME.to_string());
+	}
+	mods.sort_by_cached_key(|name| name.to_uppercase());
+	Ok(mods)
+}
+
+// lowercase prefixes of the folders sync clients create, e.g. "OneDrive - Company"
+const SYNCED_FOLDER_PREFIXES: [&str; 5] = [
+	"onedrive",
+	"dropbox",
+	"google drive",
+	"googledrive",
+	"my drive",
+];
+
+// a sync client can lock or roll back the generated mod while it's being written
+fn find_synced_folder(path: &Path) -> Option<String> {
+	path.components().find_map(|component| {
+		let name = component.as_os_str().to_str()?;
+		let lowercase = name.to_lowercase();
+		SYNCED_FOLDER_PREFIXES
+			.iter()
+			.any(|prefix| lowercase.starts_with(prefix))
+			.then(|| name.to_string())
+	})
+}
+
+// BB only loads mods from the data folder, so if it has no mods in it the generated mod
+// probably ends up somewhere the user isn't actually loading mods from
+fn check_data_folder(data_path: &DataPath) -> Result<()> {
+	std::fs::metadata(data_path).context("Couldn't read data folder")?;
+	config::check_writable(data_path.as_ref()).with_context(|| {
+		format!(
+			"Data folder {} isn't writable",
+			data_path.as_ref().display()
+		)
+	})?;
+	if let Some(folder) = find_synced_folder(data_path.as_ref()) {
+		tracing::warn!(
+			"Data folder is inside {}, which is synced and may remove or revert the preload mod, consider moving the game elsewhere",
+			folder
+		);
+	}
+	if list_mods(data_path)?.is_empty() {
+		tracing::warn!(
+			"No mods found in {}, the preload mod will only work if your mods are in this folder",
+			data_path.as_ref().display()
+		);
+	}
 	Ok(())
 }
 
-pub fn sync_gather_and_create_mod(data_path: &DataPath) -> Result<()> {
-	let resources = get_resource_handler(data_path)?;
-	create_mod(data_path, &resources)
+// the mod the patcher would write and how it compares to the current one, without writing anything
+// summary.rewritten is whether the current mod would be replaced
+pub fn preview_mod(
+	mods_path: &DataPath,
+	staging_path: Option<&DataPath>,
+	data_path: &DataPath,
+	excluded_mods: &[String],
+	verbose: bool,
+	operation: Option<&Operation>,
+) -> Result<(ResourceHandler, PatchSummary)> {
+	let (resources, mut summary) =
+		get_resource_handler(mods_path, staging_path, excluded_mods, operation)?;
+	let resources = if verbose {
+		resources
+	} else {
+		resources.without_sources()
+	};
+	// a missing or unreadable previous mod is simply rewritten
+	let previous = read_generated_mod(data_path).ok();
+	summary.existing = existing_mod(data_path);
+	summary.changes = previous
+		.as_ref()
+		.map(|previous| ResourceChanges::between(previous, &resources));
+	summary.rewritten =
+		!previous.is_some_and(|previous| previous == GeneratedMod::from(&resources));
+	Ok((resources, summary))
+}
+
+pub fn sync_gather_and_create_mod(
+	mods_path: &DataPath,
+	staging_path: Option<&DataPath>,
+	data_path: &DataPath,
+	excluded_mods: &[String],
+	verbose: bool,
+	operation: Option<&Operation>,
+) -> Result<PatchSummary> {
+	let _span = tracing::info_span!("preload_patcher").entered();
+	check_data_folder(data_path)?;
+	let (resources, summary) = preview_mod(
+		mods_path,
+		staging_path,
+		data_path,
+		excluded_mods,
+		verbose,
+		operation,
+	)?;
+	if summary.rewritten {
+		match summary.existing {
+			ExistingMod::Missing => {}
+			ExistingMod::Generated => {
+				tracing::info!(
+					"Replacing the existing preload mod: {}",
+					summary.describe_changes()
+				)
+			}
+			ExistingMod::Foreign => tracing::warn!(
+				"Replacing {}, which wasn't made by the launcher, its contents will be lost",
+				ZIP_NAME
+			),
+		}
+		create_mod(data_path, &resources)?;
+	} else {
+		PRELOAD_STALE.store(false, Ordering::Relaxed);
+	}
+	Ok(summary)
 }
 
 pub async fn async_gather_and_create_mod(config: ReadOnlySignal<Config, SyncStorage>) {
+	let operation = Operation::start_cancellable("Running Preload Patcher...");
 	let data_path = match config.read().get_bb_data_path() {
 		Some(path) => path,
 		None => {
@@ -177,9 +1143,50 @@ pub async fn async_gather_and_create_mod(config: ReadOnlySignal<Config, SyncStor
 			return;
 		}
 	};
-	match sync_gather_and_create_mod(&data_path) {
-		Ok(_) => {
-			tracing::info!("Patcher Succeeded");
+	let mods_path = match config.read().get_mods_path() {
+		Some(path) => path,
+		None => {
+			tracing::error!(
+				"Couldn't find mods in {:?}, check mods_dir_override in config.toml",
+				config.read().get_mods_dir_override()
+			);
+			return;
+		}
+	};
+	let staging_path = config.read().get_staging_mods_path();
+	let excluded_mods = config.read().get_excluded_mods().to_vec();
+	let verbose = config.read().verbose_patcher();
+	match sync_gather_and_create_mod(
+		&mods_path,
+		staging_path.as_ref(),
+		&data_path,
+		&excluded_mods,
+		verbose,
+		Some(&operation),
+	) {
+		Ok(summary) => {
+			// an empty mod is still written, but reporting it as a success hides a misconfigured data folder
+			if summary.has_resources() {
+				tracing::info!(
+					"Patcher Succeeded: scanned {} mods, {} ({})",
+					summary.mods_scanned,
+					summary.describe_counts(),
+					summary.describe_changes()
+				);
+			} else {
+				tracing::warn!("No mods found to patch — is your data folder correct?");
+			}
+			if !summary.mods_skipped.is_empty() {
+				let names: Vec<_> = summary.mods_skipped.iter().map(SkippedMod::name).collect();
+				tracing::error!(
+					"Patcher skipped {} mod(s) that couldn't be read: {}",
+					summary.mods_skipped.len(),
+					names.join(", ")
+				);
+			}
+		}
+		Err(e) if log::is_cancelled(&e) => {
+			tracing::info!("Cancelled the Preload Patcher, the preload mod was left unchanged");
 		}
 		Err(e) => {
 			tracing::error!("Patcher failed: {}", e);
@@ -190,3 +1197,650 @@ pub async fn async_gather_and_create_mod(config: ReadOnlySignal<Config, SyncStor
 pub async fn mt_gather_and_create_mod(config: ReadOnlySignal<Config, SyncStorage>) {
 	let _ = tokio::spawn(async move { async_gather_and_create_mod(config).await }).await;
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::temp_dir::TempDir;
+
+	fn hook(name: &str) -> &'static Hook {
+		HOOKS.iter().find(|hook| hook.name == name).unwrap()
+	}
+
+	fn handler(on_running: &[&str], on_start: &[&str]) -> ResourceHandler {
+		let mut gatherer = ResourceGatherer::new();
+		for line in on_running {
+			ResourceGatherer::insert_line(gatherer.hook_mut(hook("on_running")), line);
+		}
+		for line in on_start {
+			ResourceGatherer::insert_line(gatherer.hook_mut(hook("on_start")), line);
+		}
+		ResourceHandler::from(gatherer).without_sources()
+	}
+
+	#[test]
+	fn parse_priority_prefix() {
+		assert_eq!(
+			parse_resource_line("10:scripts/foo.nut"),
+			(10, "scripts/foo.nut")
+		);
+		assert_eq!(
+			parse_resource_line("scripts/foo.nut"),
+			(DEFAULT_PRIORITY, "scripts/foo.nut")
+		);
+		assert_eq!(
+			parse_resource_line("gfx/a:b.png"),
+			(DEFAULT_PRIORITY, "gfx/a:b.png")
+		);
+		assert_eq!(
+			parse_resource_line(":foo.nut"),
+			(DEFAULT_PRIORITY, ":foo.nut")
+		);
+	}
+
+	#[test]
+	fn mixed_priorities_are_sorted() {
+		let resources = handler(
+			&[
+				"b.nut", "90:a.nut", "10:z.nut", "a.nut", "50:c.nut", "10:y.nut",
+			],
+			&[],
+		);
+		// a.nut keeps the earlier of its two priorities
+		assert_eq!(
+			resources.resources(hook("on_running")),
+			vec!["y.nut", "z.nut", "a.nut", "b.nut", "c.nut"]
+		);
+		assert_eq!(
+			resources.get_raw(hook("on_running")),
+			"y.nut\nz.nut\na.nut\nb.nut\nc.nut\n"
+		);
+	}
+
+	#[test]
+	fn generated_mod_is_valid() {
+		let resources = handler(&["gfx/a.png", "gfx/b.png"], &[]);
+		validate_squirrel(&get_mod_string(&resources)).unwrap();
+	}
+
+	#[test]
+	fn template_placeholders_are_replaced() {
+		for placeholder in HOOKS.iter().map(|hook| hook.placeholder).chain([
+			"$Version$",
+			"$Name$",
+			"$ID$",
+			"$NameSpace$",
+		]) {
+			assert!(MOD_STRING.contains(placeholder), "{} missing", placeholder);
+		}
+		let mod_string = get_mod_string(&handler(&["gfx/a.png"], &["scripts/b.nut"]));
+		assert!(!mod_string.contains('$'), "{}", mod_string);
+		assert!(mod_string.contains(&format!("ID = \"{}\"", MOD_ID)));
+		assert!(mod_string.contains(&format!(
+			"OnRunning = [{0}\"gfx/a.png\",\n\t\t]",
+			TABBED_NEWLINE
+		)));
+		assert!(mod_string.contains(&format!(
+			"OnStart = [{0}\"scripts/b.nut\",\n\t\t]",
+			TABBED_NEWLINE
+		)));
+	}
+
+	#[test]
+	fn unbalanced_squirrel_is_invalid() {
+		assert!(validate_squirrel("::A <- { B = [1, 2 }").is_err());
+		assert!(validate_squirrel("::A <- { B = \"unterminated }").is_err());
+		assert!(validate_squirrel("::A <- { B = [1, 2]").is_err());
+		validate_squirrel("::A <- { B = \"[{(\" }").unwrap();
+	}
+
+	#[test]
+	fn mods_in_data_folder_are_listed() {
+		let dir = TempDir::new("data_folder");
+		let data_path = DataPath::new(dir.to_path_buf());
+		std::fs::write(dir.join("data_001.dat"), b"").unwrap();
+		std::fs::write(dir.join(ZIP_NAME), b"").unwrap();
+		assert!(list_mods(&data_path).unwrap().is_empty());
+		std::fs::write(dir.join("mod_msu.zip"), b"").unwrap();
+		assert_eq!(
+			list_mods(&data_path).unwrap(),
+			vec!["mod_msu.zip".to_string()]
+		);
+	}
+
+	#[test]
+	fn load_order_matches_bb() {
+		let dir = TempDir::new("load_order");
+		let data_path = DataPath::new(dir.to_path_buf());
+		for name in [
+			ZIP_NAME,
+			"mod_b.zip",
+			"MOD_A.zip",
+			"mod_c.ZIP",
+			"!mod_first.zip",
+			"notes.txt",
+		] {
+			std::fs::write(dir.join(name), b"").unwrap();
+		}
+		assert_eq!(
+			load_order(&data_path).unwrap(),
+			vec![
+				"!mod_first.zip",
+				"MOD_A.zip",
+				"mod_b.zip",
+				"mod_c.ZIP",
+				ZIP_NAME
+			]
+		);
+	}
+
+	#[test]
+	fn renamed_output_is_ignored() {
+		let dir = TempDir::new("renamed_output");
+		let data_path = DataPath::new(dir.to_path_buf());
+		create_mod(&data_path, &handler(&["scripts/a.nut"], &["scripts/b.nut"])).unwrap();
+		let renamed = dir.join("mod_renamed.zip");
+		std::fs::rename(dir.join(ZIP_NAME), &renamed).unwrap();
+
+		let mut gatherer = ResourceGatherer::new();
+		gather_resources_for_mod(&mut gatherer, &renamed).unwrap();
+		assert!(gatherer.hooks.values().all(HashMap::is_empty));
+	}
+
+	#[test]
+	fn created_mod_contents() {
+		let dir = TempDir::new("create_mod");
+		let out_path = dir.join("out.zip");
+		let resources = handler(&["gfx/a.png", "10:gfx/b.png"], &["scripts/c.nut"]);
+		create_mod_at(&out_path, &resources).unwrap();
+
+		let mut zip_file = ZipArchive::new(File::open(&out_path).unwrap()).unwrap();
+		assert_eq!(zip_file.len(), 3);
+		assert_eq!(
+			read_file_in_zip(&mut zip_file, ON_RUNNING_PATH).unwrap(),
+			"gfx/b.png\ngfx/a.png\n"
+		);
+		assert_eq!(
+			read_file_in_zip(&mut zip_file, ON_START_PATH).unwrap(),
+			"scripts/c.nut\n"
+		);
+		assert_eq!(
+			read_file_in_zip(&mut zip_file, &get_preload_path()).unwrap(),
+			get_mod_string(&resources)
+		);
+		assert!(is_generated_mod(&mut zip_file).unwrap());
+	}
+
+	#[test]
+	fn generated_mod_is_read_back() {
+		let dir = TempDir::new("read_generated_mod");
+		let data_path = DataPath::new(dir.to_path_buf());
+		assert!(read_generated_mod(&data_path).is_err());
+
+		let resources = handler(&["gfx/a.png"], &["scripts/b.nut"]);
+		create_mod(&data_path, &resources).unwrap();
+		let generated_mod = read_generated_mod(&data_path).unwrap();
+		assert_eq!(generated_mod.mod_string, get_mod_string(&resources));
+		assert_eq!(
+			generated_mod.hooks,
+			vec![
+				(hook("on_running"), "gfx/a.png\n".to_owned()),
+				(hook("on_start"), "scripts/b.nut\n".to_owned()),
+			]
+		);
+	}
+
+	fn write_mod(path: &Path, files: &[(&str, &str)]) {
+		let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+		for (name, contents) in files {
+			zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+			zip.write_all(contents.as_bytes()).unwrap();
+		}
+		zip.finish().unwrap();
+	}
+
+	#[test]
+	fn generated_mod_is_skipped_by_name() {
+		let dir = TempDir::new("tricky_names");
+		let data_path = DataPath::new(dir.to_path_buf());
+		// not a valid zip, so it would only count as scanned if the name check missed it
+		std::fs::write(dir.join(ZIP_NAME), b"").unwrap();
+		write_mod(
+			&dir.join(format!("mod_ü{}", ZIP_NAME)),
+			&[(ON_RUNNING_PATH, "gfx/a.png")],
+		);
+		#[cfg(unix)]
+		{
+			use std::os::unix::ffi::OsStrExt;
+			let name = std::ffi::OsStr::from_bytes(b"mod_\xFF~mod_msu_launcher.zip");
+			write_mod(&dir.join(name), &[(ON_RUNNING_PATH, "gfx/b.png")]);
+		}
+
+		let (resources, summary) = get_resource_handler(&data_path, None, &[], None).unwrap();
+		let expected = if cfg!(unix) { 2 } else { 1 };
+		assert_eq!(summary.mods_scanned, expected);
+		assert_eq!(resources.resources(hook("on_running")).len(), expected);
+		assert!(summary.mods_skipped.is_empty());
+		assert_eq!(list_mods(&data_path).unwrap().len(), expected);
+	}
+
+	#[test]
+	fn resources_from_manifest() {
+		let dir = TempDir::new("manifest");
+		let manifest_only = dir.join("mod_manifest.zip");
+		write_mod(
+			&manifest_only,
+			&[(
+				MANIFEST_PATH,
+				r#"{ "on_running": ["gfx/a.png", { "name": "gfx/b.png", "priority": 10 }] }"#,
+			)],
+		);
+		let mut gatherer = ResourceGatherer::new();
+		gather_resources_for_mod(&mut gatherer, &manifest_only).unwrap();
+		assert_eq!(
+			ResourceHandler::from(gatherer).resources(hook("on_running")),
+			vec!["gfx/b.png", "gfx/a.png"]
+		);
+
+		let both = dir.join("mod_both.zip");
+		write_mod(
+			&both,
+			&[
+				(ON_START_PATH, "scripts/a.nut\n90:scripts/b.nut"),
+				(
+					MANIFEST_PATH,
+					r#"{ "on_start": ["10:scripts/b.nut", { "name": "scripts/c.nut" }] }"#,
+				),
+			],
+		);
+		let mut gatherer = ResourceGatherer::new();
+		gather_resources_for_mod(&mut gatherer, &both).unwrap();
+		// the manifest's earlier priority for b.nut wins over the text file's
+		assert_eq!(
+			ResourceHandler::from(gatherer).resources(hook("on_start")),
+			vec!["scripts/b.nut", "scripts/a.nut", "scripts/c.nut"]
+		);
+
+		let invalid = dir.join("mod_invalid.zip");
+		write_mod(&invalid, &[(MANIFEST_PATH, "{ not json")]);
+		assert!(gather_resources_for_mod(&mut ResourceGatherer::new(), &invalid).is_err());
+	}
+
+	#[test]
+	fn sources_are_attributed() {
+		let dir = TempDir::new("sources");
+		write_mod(
+			&dir.join("mod_a.zip"),
+			&[(ON_RUNNING_PATH, "gfx/shared.png\ngfx/a.png")],
+		);
+		write_mod(
+			&dir.join("mod_b.zip"),
+			&[
+				(ON_RUNNING_PATH, "gfx/shared.png"),
+				(ON_START_PATH, "scripts/b.nut"),
+			],
+		);
+		let mut gatherer = ResourceGatherer::new();
+		gather_resources_for_mod(&mut gatherer, &dir.join("mod_a.zip")).unwrap();
+		gather_resources_for_mod(&mut gatherer, &dir.join("mod_b.zip")).unwrap();
+		let resources = ResourceHandler::from(gatherer);
+		assert_eq!(
+			resources.make_sources_string().unwrap(),
+			"# on_running\ngfx/a.png <- mod_a.zip\ngfx/shared.png <- mod_a.zip, mod_b.zip\n# on_start\nscripts/b.nut <- mod_b.zip\n"
+		);
+
+		let out_path = dir.join("out.zip");
+		create_mod_at(&out_path, &resources).unwrap();
+		let mut zip_file = ZipArchive::new(File::open(&out_path).unwrap()).unwrap();
+		assert!(!read_file_in_zip(&mut zip_file, SOURCES_PATH)
+			.unwrap()
+			.is_empty());
+		create_mod_at(&out_path, &resources.without_sources()).unwrap();
+		let mut zip_file = ZipArchive::new(File::open(&out_path).unwrap()).unwrap();
+		assert!(zip_file.by_name(SOURCES_PATH).is_err());
+	}
+
+	#[test]
+	fn mods_are_backed_up_and_restored() {
+		let dir = TempDir::new("mod_backup");
+		let data_dir = dir.join("data");
+		std::fs::create_dir_all(&data_dir).unwrap();
+		write_mod(
+			&data_dir.join("mod_a.zip"),
+			&[(ON_START_PATH, "scripts/a.nut")],
+		);
+		write_mod(
+			&data_dir.join("mod_b.zip"),
+			&[(ON_START_PATH, "scripts/b.nut")],
+		);
+		write_mod(&data_dir.join(ZIP_NAME), &[(ON_START_PATH, "generated")]);
+		let data_path = DataPath::new(data_dir.clone());
+
+		let archive_path = backup_mods(&data_path).unwrap();
+		assert_eq!(archive_path.parent().unwrap(), dir.join(MOD_BACKUPS_FOLDER));
+		let original = std::fs::read(data_dir.join("mod_a.zip")).unwrap();
+		std::fs::remove_file(data_dir.join("mod_a.zip")).unwrap();
+		std::fs::remove_file(data_dir.join(ZIP_NAME)).unwrap();
+
+		let restored = restore_mods(&data_path, &archive_path).unwrap();
+		assert_eq!(restored, vec!["mod_a.zip", "mod_b.zip"]);
+		assert_eq!(std::fs::read(data_dir.join("mod_a.zip")).unwrap(), original);
+		assert!(!data_dir.join(ZIP_NAME).exists());
+		assert_ne!(backup_mods(&data_path).unwrap(), archive_path);
+	}
+
+	#[test]
+	fn merged_gatherers_keep_earliest_priority() {
+		let mut base = ResourceGatherer::new();
+		base.add_mod(
+			ResourceGatherer {
+				hooks: HashMap::from([
+					(
+						"on_running",
+						HashMap::from([("gfx/shared.png".to_owned(), 50)]),
+					),
+					(
+						"on_start",
+						HashMap::from([("scripts/base.nut".to_owned(), 50)]),
+					),
+				]),
+				sources: HashMap::new(),
+			},
+			"mod_base.zip",
+		);
+		let mut staging = ResourceGatherer::new();
+		staging.add_mod(
+			ResourceGatherer {
+				hooks: HashMap::from([(
+					"on_running",
+					HashMap::from([
+						("gfx/shared.png".to_owned(), 10),
+						("gfx/staged.png".to_owned(), 20),
+					]),
+				)]),
+				sources: HashMap::new(),
+			},
+			"mod_staged.zip",
+		);
+		base.merge(staging);
+		let resources = ResourceHandler::from(base);
+		assert_eq!(
+			resources.resources(hook("on_running")),
+			vec!["gfx/shared.png", "gfx/staged.png"]
+		);
+		assert_eq!(
+			resources.resources(hook("on_start")),
+			vec!["scripts/base.nut"]
+		);
+		assert_eq!(
+			resources.make_sources_string().unwrap(),
+			"# on_running\ngfx/shared.png <- mod_base.zip, mod_staged.zip\ngfx/staged.png <- mod_staged.zip\n# on_start\nscripts/base.nut <- mod_base.zip\n"
+		);
+	}
+
+	#[test]
+	fn staging_folder_is_merged() {
+		let dir = TempDir::new("staging");
+		let staging_dir = dir.join("staging");
+		std::fs::create_dir_all(&staging_dir).unwrap();
+		write_mod(
+			&dir.join("mod_a.zip"),
+			&[(ON_RUNNING_PATH, "50:gfx/shared.png")],
+		);
+		write_mod(
+			&staging_dir.join("mod_b.zip"),
+			&[(ON_RUNNING_PATH, "10:gfx/shared.png\ngfx/b.png")],
+		);
+		let data_path = DataPath::new(dir.to_path_buf());
+		let staging_path = DataPath::new(staging_dir);
+
+		let (resources, summary) =
+			get_resource_handler(&data_path, Some(&staging_path), &[], None).unwrap();
+		assert_eq!(summary.mods_scanned, 2);
+		assert_eq!(
+			resources.resources(hook("on_running")),
+			vec!["gfx/shared.png", "gfx/b.png"]
+		);
+		assert_eq!(
+			resources.make_sources_string().unwrap(),
+			"# on_running\ngfx/shared.png <- mod_a.zip, mod_b.zip\ngfx/b.png <- mod_b.zip\n# on_start\n"
+		);
+	}
+
+	#[test]
+	fn parallel_scan_matches_serial() {
+		let dir = TempDir::new("parallel_scan");
+		for i in 0..20 {
+			let on_running = format!("gfx/shared.png\n{}:gfx/mod_{}.png", i % 3, i);
+			let on_start = format!("scripts/mod_{}.nut", i);
+			write_mod(
+				&dir.join(format!("mod_{:02}.zip", i)),
+				&[
+					(ON_RUNNING_PATH, on_running.as_str()),
+					(ON_START_PATH, on_start.as_str()),
+				],
+			);
+		}
+		write_mod(&dir.join("mod_broken.zip"), &[(MANIFEST_PATH, "{")]);
+		let data_path = DataPath::new(dir.to_path_buf());
+
+		let (serial, serial_summary) = gather_resources(&data_path, None, &[], None, 1).unwrap();
+		let (parallel, parallel_summary) =
+			gather_resources(&data_path, None, &[], None, 4).unwrap();
+		assert!(serial.hooks().eq(parallel.hooks()));
+		assert_eq!(serial.make_sources_string(), parallel.make_sources_string());
+		assert_eq!(serial_summary.mods_scanned, 21);
+		assert_eq!(parallel_summary.mods_scanned, 21);
+		let skipped = |summary: &PatchSummary| -> Vec<String> {
+			summary.mods_skipped.iter().map(SkippedMod::name).collect()
+		};
+		assert_eq!(skipped(&parallel_summary), vec!["mod_broken.zip"]);
+		assert_eq!(skipped(&serial_summary), skipped(&parallel_summary));
+	}
+
+	#[test]
+	fn cancelled_scan_returns_cancelled() {
+		let dir = TempDir::new("cancelled_scan");
+		write_mod(&dir.join("mod_a.zip"), &[(ON_START_PATH, "scripts/a.nut")]);
+		let data_path = DataPath::new(dir.to_path_buf());
+
+		let operation = Operation::start_cancellable("Running Preload Patcher...");
+		operation.cancel();
+		// starting another operation doesn't clear the cancel, or cancel the new one
+		let other = Operation::start_cancellable("Downloading Steamless...");
+		let error = gather_resources(&data_path, None, &[], Some(&operation), 2)
+			.err()
+			.unwrap();
+		assert!(log::is_cancelled(&error));
+		other.check_cancelled().unwrap();
+		drop(operation);
+	}
+
+	#[test]
+	fn unchanged_mod_is_not_rewritten() {
+		let dir = TempDir::new("unchanged_mod");
+		write_mod(
+			&dir.join("mod_a.zip"),
+			&[(ON_RUNNING_PATH, "gfx/a.png\ngfx/b.png")],
+		);
+		let data_path = DataPath::new(dir.to_path_buf());
+
+		let summary =
+			sync_gather_and_create_mod(&data_path, None, &data_path, &[], false, None).unwrap();
+		assert!(summary.changes.is_none());
+		assert!(summary.rewritten);
+
+		let summary =
+			sync_gather_and_create_mod(&data_path, None, &data_path, &[], false, None).unwrap();
+		assert!(summary.changes.unwrap().is_empty());
+		assert!(!summary.rewritten);
+
+		write_mod(
+			&dir.join("mod_a.zip"),
+			&[
+				(ON_RUNNING_PATH, "gfx/a.png"),
+				(ON_START_PATH, "scripts/a.nut\nscripts/b.nut\nscripts/c.nut"),
+			],
+		);
+		let summary =
+			sync_gather_and_create_mod(&data_path, None, &data_path, &[], false, None).unwrap();
+		assert!(summary.rewritten);
+		assert_eq!(
+			summary.changes.unwrap().to_string(),
+			"+3 on_start, -1 on_running"
+		);
+
+		// only the sources file differs, which still needs writing
+		let summary =
+			sync_gather_and_create_mod(&data_path, None, &data_path, &[], true, None).unwrap();
+		assert!(summary.changes.unwrap().is_empty());
+		assert!(summary.rewritten);
+	}
+
+	#[test]
+	fn preview_does_not_write() {
+		let dir = TempDir::new("preview");
+		write_mod(&dir.join("mod_a.zip"), &[(ON_RUNNING_PATH, "gfx/a.png")]);
+		let data_path = DataPath::new(dir.to_path_buf());
+
+		let (resources, summary) =
+			preview_mod(&data_path, None, &data_path, &[], false, None).unwrap();
+		assert_eq!(resources.resources(hook("on_running")), vec!["gfx/a.png"]);
+		assert_eq!(summary.describe_pending_changes(), "new preload mod");
+		assert!(!dir.join(ZIP_NAME).exists());
+
+		sync_gather_and_create_mod(&data_path, None, &data_path, &[], false, None).unwrap();
+		let (_, summary) = preview_mod(&data_path, None, &data_path, &[], false, None).unwrap();
+		assert_eq!(summary.describe_pending_changes(), "nothing would change");
+
+		write_mod(&dir.join("mod_b.zip"), &[(ON_START_PATH, "scripts/b.nut")]);
+		let before = std::fs::read(dir.join(ZIP_NAME)).unwrap();
+		let (_, summary) = preview_mod(&data_path, None, &data_path, &[], false, None).unwrap();
+		assert_eq!(summary.describe_pending_changes(), "+1 on_start");
+		assert_eq!(std::fs::read(dir.join(ZIP_NAME)).unwrap(), before);
+	}
+
+	#[test]
+	fn mod_metadata_is_parsed() {
+		assert_eq!(
+			parse_mod_metadata(
+				"::ModA <- {\n\tID = \"mod_a\",\n\tVersion = \"1.2.0\",\n\tName = \"Mod A\",\n}\n::Hooks.register(::ModA.ID, ::ModA.Version, ::ModA.Name);"
+			),
+			ModMetadata {
+				id: Some("mod_a".to_owned()),
+				name: Some("Mod A".to_owned()),
+				version: Some("1.2.0".to_owned()),
+				update_source: None,
+			}
+		);
+		assert_eq!(
+			parse_mod_metadata("::mods_registerMod(\"mod_b\", 1.5, \"Mod B\");"),
+			ModMetadata {
+				id: Some("mod_b".to_owned()),
+				name: Some("Mod B".to_owned()),
+				version: Some("1.5".to_owned()),
+				update_source: None,
+			}
+		);
+		assert_eq!(
+			parse_mod_metadata(
+				"::ModC.Mod <- ::MSU.Class.Mod(\"mod_c\", \"0.3.0\", \"Mod C\");\n::ModC.Mod.Registry.addModSource(::MSU.System.Registry.ModSourceDomain.GitHub, \"https://github.com/me/mod_c\");"
+			)
+			.update_source
+			.as_deref(),
+			Some("https://github.com/me/mod_c")
+		);
+		assert!(parse_mod_metadata("::NameSpace <- {};").is_empty());
+
+		let dir = TempDir::new("mod_metadata");
+		write_mod(
+			&dir.join("mod_b.zip"),
+			&[(
+				"scripts/!mods_preload/mod_b.nut",
+				"::MSU.Class.Mod(\"mod_b\", \"2.0.0\", \"Mod B\");",
+			)],
+		);
+		write_mod(
+			&dir.join("mod_plain.zip"),
+			&[(ON_RUNNING_PATH, "gfx/a.png")],
+		);
+		std::fs::write(dir.join("mod_broken.zip"), b"").unwrap();
+		let mods = list_mods_with_metadata(&DataPath::new(dir.to_path_buf())).unwrap();
+		let versions: Vec<_> = mods
+			.iter()
+			.map(|info| {
+				let version = info.metadata.as_ref().and_then(|m| m.version.as_deref());
+				(info.file_name.as_str(), version)
+			})
+			.collect();
+		assert_eq!(
+			versions,
+			vec![
+				("mod_b.zip", Some("2.0.0")),
+				("mod_broken.zip", None),
+				("mod_plain.zip", None)
+			]
+		);
+	}
+
+	#[test]
+	fn foreign_mod_is_detected() {
+		let dir = TempDir::new("foreign_mod");
+		let data_path = DataPath::new(dir.to_path_buf());
+		assert_eq!(existing_mod(&data_path), ExistingMod::Missing);
+
+		write_mod(
+			&dir.join(ZIP_NAME),
+			&[(ON_RUNNING_PATH, "gfx/hand_made.png")],
+		);
+		assert_eq!(existing_mod(&data_path), ExistingMod::Foreign);
+		let (_, summary) = preview_mod(&data_path, None, &data_path, &[], false, None).unwrap();
+		assert!(summary.rewritten);
+		assert!(summary
+			.describe_pending_changes()
+			.contains("wasn't made by the launcher"));
+
+		let summary =
+			sync_gather_and_create_mod(&data_path, None, &data_path, &[], false, None).unwrap();
+		assert_eq!(summary.existing, ExistingMod::Foreign);
+		assert!(summary.describe_changes().starts_with("replaced"));
+		assert_eq!(existing_mod(&data_path), ExistingMod::Generated);
+	}
+
+	#[test]
+	fn synced_folders_are_detected() {
+		assert_eq!(
+			find_synced_folder(Path::new("C:/Users/me/OneDrive - Company/Games/BB/data")),
+			Some("OneDrive - Company".to_string())
+		);
+		assert_eq!(
+			find_synced_folder(Path::new("G:/My Drive/Battle Brothers/data")),
+			Some("My Drive".to_string())
+		);
+		assert_eq!(
+			find_synced_folder(Path::new("C:/Steam/steamapps/common/Battle Brothers/data")),
+			None
+		);
+	}
+
+	#[test]
+	fn unquotable_resources_skip_their_mod() {
+		assert!(check_resource_name("gfx/\"quoted\".png").is_err());
+		assert!(check_resource_name("gfx\\backslash.png").is_err());
+		check_resource_name("gfx/plain name.png").unwrap();
+
+		let dir = TempDir::new("unquotable_resources");
+		write_mod(&dir.join("mod_a.zip"), &[(ON_RUNNING_PATH, "gfx/a.png")]);
+		write_mod(
+			&dir.join("mod_b.zip"),
+			&[(ON_START_PATH, "scripts/b.nut\nscripts/\"b\".nut")],
+		);
+		let data_path = DataPath::new(dir.to_path_buf());
+		let (resources, summary) = gather_resources(&data_path, None, &[], None, 1).unwrap();
+		assert_eq!(summary.mods_skipped.len(), 1);
+		assert_eq!(summary.mods_skipped[0].name(), "mod_b.zip");
+		assert_eq!(resources.get_raw(hook("on_running")), "gfx/a.png\n");
+		assert_eq!(resources.get_raw(hook("on_start")), "");
+		create_mod_at(&dir.join("out.zip"), &resources).unwrap();
+	}
+}
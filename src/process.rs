@@ -0,0 +1,112 @@
+use std::{
+	process::Child,
+	time::{Duration, Instant},
+};
+
+#[cfg(target_os = "windows")]
+pub fn is_process_running(exe_name: &str) -> bool {
+	use std::mem::size_of;
+	use windows::Win32::Foundation::CloseHandle;
+	use windows::Win32::System::Diagnostics::ToolHelp::{
+		CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W,
+		TH32CS_SNAPPROCESS,
+	};
+
+	let snapshot = match unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) } {
+		Ok(snapshot) => snapshot,
+		Err(e) => {
+			tracing::warn!("Couldn't enumerate processes: {}", e);
+			return false;
+		}
+	};
+	let mut entry = PROCESSENTRY32W {
+		dwSize: size_of::<PROCESSENTRY32W>() as u32,
+		..Default::default()
+	};
+	let mut found = false;
+	let mut next = unsafe { Process32FirstW(snapshot, &mut entry) };
+	while next.is_ok() {
+		let len = entry
+			.szExeFile
+			.iter()
+			.position(|&c| c == 0)
+			.unwrap_or(entry.szExeFile.len());
+		if String::from_utf16_lossy(&entry.szExeFile[..len]).eq_ignore_ascii_case(exe_name) {
+			found = true;
+			break;
+		}
+		next = unsafe { Process32NextW(snapshot, &mut entry) };
+	}
+	let _ = unsafe { CloseHandle(snapshot) };
+	found
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn is_process_running(_exe_name: &str) -> bool {
+	false
+}
+
+pub fn is_steam_running() -> bool {
+	is_process_running("steam.exe")
+}
+
+// another process, usually the game or an antivirus scan, has the file open
+pub fn is_file_locked(error: &std::io::Error) -> bool {
+	use windows::Win32::Foundation::{ERROR_LOCK_VIOLATION, ERROR_SHARING_VIOLATION};
+	matches!(
+		error.raw_os_error(),
+		Some(code) if code == ERROR_SHARING_VIOLATION.0 as i32 || code == ERROR_LOCK_VIOLATION.0 as i32
+	)
+}
+
+// polled, since a launch through Steam doesn't give us the game's process
+#[cfg(target_os = "windows")]
+pub async fn wait_for_process(exe_name: &str, timeout: Duration) -> bool {
+	let started = Instant::now();
+	while started.elapsed() < timeout {
+		if is_process_running(exe_name) {
+			return true;
+		}
+		tokio::time::sleep(Duration::from_secs(1)).await;
+	}
+	false
+}
+
+// processes can't be listed here, so assume it started
+#[cfg(not(target_os = "windows"))]
+pub async fn wait_for_process(_exe_name: &str, _timeout: Duration) -> bool {
+	true
+}
+
+// exiting with an error this soon after starting usually means a missing 4GB patch or a mod conflict
+const EARLY_EXIT: Duration = Duration::from_secs(60);
+
+pub async fn watch_game(mut child: Child) {
+	let started = Instant::now();
+	let status = match tokio::task::spawn_blocking(move || child.wait()).await {
+		Ok(Ok(status)) => status,
+		Ok(Err(e)) => {
+			tracing::warn!("Lost track of Battle Brothers: {}", e);
+			return;
+		}
+		Err(e) => {
+			tracing::warn!("Lost track of Battle Brothers: {}", e);
+			return;
+		}
+	};
+	if status.success() {
+		tracing::info!("Battle Brothers exited");
+		return;
+	}
+	let code = status
+		.code()
+		.map_or_else(|| "unknown".to_string(), |code| format!("{:#X}", code));
+	if started.elapsed() < EARLY_EXIT {
+		tracing::error!(
+			"Battle Brothers crashed shortly after starting (exit code {}), check that the 4GB Patch is applied and look for mod conflicts",
+			code
+		);
+	} else {
+		tracing::error!("Battle Brothers exited with code {}", code);
+	}
+}
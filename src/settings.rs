@@ -0,0 +1,353 @@
+use dioxus::prelude::*;
+use std::path::PathBuf;
+
+use crate::{
+	button::{
+		self, Button, CheckSaveRoundTripButton, PatchSpecificExeButton, RedetectGameButton,
+		ReinstallSteamlessButton, RestoreExeButton, SelfTestButton,
+	},
+	config::Config,
+	generated_mod::{PreviewPreloadButton, ShowGeneratedModButton},
+	i18n::tr,
+	log,
+	mod_list::ShowModListButton,
+	modal, patcher_preload,
+};
+
+fn toggle_mod_excluded(mut config: SyncSignal<Config>, mod_name: &str) {
+	let excluded = config
+		.read()
+		.get_excluded_mods()
+		.iter()
+		.any(|name| name == mod_name);
+	match config.with_mut(|c| c.set_mod_excluded(mod_name, !excluded)) {
+		// the generated mod still has the excluded mod's resources, or lacks the included one's
+		Ok(_) => patcher_preload::mark_preload_stale("Excluded mods changed"),
+		Err(e) => tracing::error!("Couldn't save excluded mods: {}", e),
+	}
+}
+
+#[component]
+fn ModExclusionList(config: SyncSignal<Config>) -> Element {
+	let mods = use_memo(move || {
+		config
+			.read()
+			.get_mods_path()
+			.map(|mods_path| patcher_preload::list_mods(&mods_path))
+	});
+	let mods = match &*mods.read() {
+		Some(Ok(mods)) => mods.clone(),
+		Some(Err(e)) => {
+			return rsx!( p { {format!("{}: {}", tr("settings_mods_folder_error"), e)} } )
+		}
+		None => return rsx!( p { {tr("settings_mods_folder_missing")} } ),
+	};
+	rsx!(
+		p { class: "text-gray-300", {tr("settings_included_mods")} }
+		div { class: "flex flex-col max-h-64 overflow-y-auto",
+			for mod_name in mods {
+				label { key: "{mod_name}",
+					input {
+						r#type: "checkbox",
+						checked: !config.read().get_excluded_mods().contains(&mod_name),
+						onchange: {
+							let mod_name = mod_name.clone();
+							move |_| toggle_mod_excluded(config, &mod_name)
+						}
+					}
+					" {mod_name}"
+				}
+			}
+		}
+	)
+}
+
+fn toggle_auto_preload(mut config: SyncSignal<Config>) {
+	let enabled = !config.read().auto_preload_on_launch();
+	if let Err(e) = config.with_mut(|c| c.set_auto_preload_on_launch(enabled)) {
+		tracing::error!("Couldn't save settings: {}", e);
+	}
+}
+
+fn toggle_launch_unpacked_exe(mut config: SyncSignal<Config>) {
+	let enabled = !config.read().launch_unpacked_exe();
+	if let Err(e) = config.with_mut(|c| c.set_launch_unpacked_exe(enabled)) {
+		tracing::error!("Couldn't save settings: {}", e);
+	}
+}
+
+fn toggle_make_backups(mut config: SyncSignal<Config>) {
+	let enabled = !config.read().make_backups();
+	if let Err(e) = config.with_mut(|c| c.set_make_backups(enabled)) {
+		tracing::error!("Couldn't save settings: {}", e);
+	}
+}
+
+const EXPORTED_CONFIG_NAME: &str = "msu_launcher_config.toml";
+
+fn export_config(config: SyncSignal<Config>, e: Event<FormData>) {
+	let Some(folder) = button::get_first_file(&e) else {
+		return;
+	};
+	let path = folder.join(EXPORTED_CONFIG_NAME);
+	match config.read().export_to(&path) {
+		Ok(_) => tracing::info!("Exported config to {}", path.display()),
+		Err(e) => tracing::error!("Couldn't export config: {:#}", e),
+	}
+}
+
+fn import_config(mut config: SyncSignal<Config>, e: Event<FormData>) {
+	let Some(path) = button::get_first_file(&e) else {
+		return;
+	};
+	if let Err(e) = config.with_mut(|c| c.import_from(&path)) {
+		tracing::error!("Couldn't import config: {:#}", e);
+		return;
+	}
+	let config = config.read();
+	match config.get_bb_path() {
+		Some(bb_path) if config.get_bb_data_path().is_none() => tracing::warn!(
+			"Imported config, but the game isn't at {} on this machine, set the game location",
+			bb_path.display()
+		),
+		_ => tracing::info!("Imported config from {}", path.display()),
+	}
+}
+
+fn copy_load_order(config: SyncSignal<Config>) {
+	let Some(data_path) = config.read().get_bb_data_path() else {
+		tracing::error!("Couldn't find /data folder");
+		return;
+	};
+	match patcher_preload::load_order(&data_path) {
+		Ok(mods) => {
+			let text: Vec<_> = mods
+				.iter()
+				.enumerate()
+				.map(|(i, name)| format!("{}. {}", i + 1, name))
+				.collect();
+			let text = serde_json::to_string(&text.join("\n")).unwrap_or_default();
+			let _ = eval(&format!("navigator.clipboard.writeText({});", text));
+			tracing::info!(
+				"Copied the load order of {} mods to the clipboard",
+				mods.len()
+			);
+		}
+		Err(e) => tracing::error!("Couldn't read load order: {}", e),
+	}
+}
+
+fn open_config_file(config: SyncSignal<Config>) {
+	match config.read().open_config_file() {
+		Ok(path) => tracing::info!("Opened {}", path.display()),
+		Err(e) => tracing::error!("{:#}", e),
+	}
+}
+
+fn reload_config(mut config: SyncSignal<Config>) {
+	match config.with_mut(|c| c.reload()) {
+		Ok(_) => tracing::info!("Reloaded config.toml"),
+		Err(e) => tracing::error!("Couldn't reload config: {:#}", e),
+	}
+}
+
+async fn backup_mods(config: SyncSignal<Config>) {
+	let Some(mods_path) = config.read().get_mods_path() else {
+		tracing::error!("Couldn't find mods folder");
+		return;
+	};
+	let _operation = log::Operation::start("Backing up mods...");
+	match tokio::task::spawn_blocking(move || patcher_preload::backup_mods(&mods_path)).await {
+		Ok(Ok(archive_path)) => tracing::info!("Backed up mods to {}", archive_path.display()),
+		Ok(Err(e)) => tracing::error!("Couldn't back up mods: {:#}", e),
+		Err(e) => tracing::error!("Couldn't back up mods: {}", e),
+	}
+}
+
+async fn restore_mods(config: SyncSignal<Config>, archive_path: PathBuf) {
+	let Some(mods_path) = config.read().get_mods_path() else {
+		tracing::error!("Couldn't find mods folder");
+		return;
+	};
+	let _operation = log::Operation::start("Restoring mods...");
+	match tokio::task::spawn_blocking(move || {
+		patcher_preload::restore_mods(&mods_path, &archive_path)
+	})
+	.await
+	{
+		Ok(Ok(restored)) => tracing::info!(
+			"Restored {} mods, run Preload Patcher to apply changes",
+			restored.len()
+		),
+		Ok(Err(e)) => tracing::error!("Couldn't restore mods: {:#}", e),
+		Err(e) => tracing::error!("Couldn't restore mods: {}", e),
+	}
+}
+
+#[component]
+fn ModBackupButtons(config: SyncSignal<Config>) -> Element {
+	let restore_id = "hidden-restore-mods-input-id";
+	let disabled = use_memo(move || config.read().get_mods_path().is_none());
+	rsx!(
+		input {
+			id: restore_id,
+			r#type: "file",
+			accept: ".zip",
+			multiple: "false",
+			hidden: true,
+			onchange: move |e| {
+				if let Some(archive_path) = button::get_first_file(&e) {
+					spawn(restore_mods(config, archive_path));
+				}
+			}
+		}
+		div { class: "flex space-x-2",
+			Button {
+				class: "p-1 flex-grow",
+				disabled,
+				onclick: move |_| {
+					spawn(backup_mods(config));
+				},
+				{tr("settings_backup_mods")}
+			}
+			Button {
+				class: "p-1 flex-grow",
+				disabled,
+				onclick: move |_| {
+					eval(&format!("document.getElementById('{}').click();", restore_id));
+				},
+				{tr("settings_restore_mods")}
+			}
+		}
+	)
+}
+
+#[component]
+fn ConfigTransferButtons(config: SyncSignal<Config>) -> Element {
+	let export_id = "hidden-export-config-input-id";
+	let import_id = "hidden-import-config-input-id";
+	rsx!(
+		input {
+			id: export_id,
+			r#type: "file",
+			directory: true,
+			multiple: "false",
+			hidden: true,
+			onchange: move |e| export_config(config, e)
+		}
+		input {
+			id: import_id,
+			r#type: "file",
+			accept: ".toml",
+			multiple: "false",
+			hidden: true,
+			onchange: move |e| import_config(config, e)
+		}
+		div { class: "flex space-x-2",
+			Button {
+				class: "p-1 flex-grow",
+				onclick: move |_| {
+					eval(&format!("document.getElementById('{}').click();", export_id));
+				},
+				{tr("settings_export_config")}
+			}
+			Button {
+				class: "p-1 flex-grow",
+				onclick: move |_| {
+					eval(&format!("document.getElementById('{}').click();", import_id));
+				},
+				{tr("settings_import_config")}
+			}
+		}
+		div { class: "flex space-x-2",
+			Button {
+				class: "p-1 flex-grow",
+				onclick: move |_| open_config_file(config),
+				{tr("settings_open_config")}
+			}
+			Button {
+				class: "p-1 flex-grow",
+				onclick: move |_| reload_config(config),
+				{tr("settings_reload_config")}
+			}
+		}
+	)
+}
+
+#[component]
+fn SettingsPanel(config: SyncSignal<Config>, on_close: EventHandler<MouseEvent>) -> Element {
+	modal::use_modal();
+	rsx!(
+		div { class: "fixed inset-0 z-10 flex justify-center items-center bg-black/70",
+			div { class: "flex flex-col space-y-2 rounded-lg bg-gray-800 p-6 normal-font text-lg",
+				h2 { class: "title-font text-3xl text-center", {tr("settings")} }
+				label {
+					input {
+						r#type: "checkbox",
+						checked: config.read().auto_preload_on_launch(),
+						onchange: move |_| toggle_auto_preload(config)
+					}
+					" "
+					{tr("settings_auto_preload")}
+				}
+				label {
+					input {
+						r#type: "checkbox",
+						checked: config.read().launch_unpacked_exe(),
+						onchange: move |_| toggle_launch_unpacked_exe(config)
+					}
+					" "
+					{tr("settings_launch_unpacked_exe")}
+				}
+				label {
+					input {
+						r#type: "checkbox",
+						checked: config.read().make_backups(),
+						onchange: move |_| toggle_make_backups(config)
+					}
+					" "
+					{tr("settings_make_backups")}
+				}
+				if !config.read().make_backups() {
+					p { class: "text-red-400 text-base", {tr("settings_backups_disabled")} }
+				}
+				ModExclusionList { config }
+				ModBackupButtons { config }
+				ShowModListButton { class: "p-1", config }
+				div { class: "flex space-x-2",
+					ShowGeneratedModButton { class: "p-1 flex-grow", config }
+					PreviewPreloadButton { class: "p-1 flex-grow", config }
+				}
+				Button {
+					class: "p-1",
+					disabled: use_memo(move || !config.read().bb_path_known()),
+					onclick: move |_| copy_load_order(config),
+					{tr("settings_copy_load_order")}
+				}
+				ConfigTransferButtons { config }
+				RedetectGameButton { class: "p-1", config }
+				ReinstallSteamlessButton { class: "p-1", config }
+				PatchSpecificExeButton { class: "p-1", config }
+				RestoreExeButton { class: "p-1", config }
+				SelfTestButton { class: "p-1", config }
+				CheckSaveRoundTripButton { class: "p-1" }
+				Button { class: "p-1 text-xl", onclick: move |e| on_close.call(e), {tr("close")} }
+			}
+		}
+	)
+}
+
+#[component]
+pub fn SettingsButton(
+	class: Option<String>,
+	style: Option<String>,
+	config: SyncSignal<Config>,
+) -> Element {
+	let mut open = use_signal(|| false);
+	rsx!(
+		Button { class, style, onclick: move |_| open.set(true), {tr("settings")} }
+		if *open.read() {
+			SettingsPanel { config, on_close: move |_| open.set(false) }
+		}
+	)
+}
@@ -1,4 +1,10 @@
-mod save_game;
-mod serialized_sq_value;
-mod shared;
-mod sq_value;
+pub mod convert;
+pub mod save_game;
+pub mod serialized_sq_value;
+pub mod shared;
+pub mod sq_value;
+
+pub use convert::{convert_saves_in_dir, ConversionSummary};
+pub use save_game::SaveGame;
+pub use shared::{Readable, Writable};
+pub use sq_value::{SQTable, SQValue};
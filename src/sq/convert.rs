@@ -0,0 +1,102 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use super::save_game::SaveGame;
+
+const SAVE_EXTENSION: &str = "sav";
+
+#[derive(Debug, Default)]
+pub struct ConversionSummary {
+	// the written json files
+	pub converted: Vec<PathBuf>,
+	// the saves that couldn't be converted, the rest of the batch still is
+	pub failed: Vec<(PathBuf, anyhow::Error)>,
+}
+
+impl std::fmt::Display for ConversionSummary {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"Converted {} saves, {} failed",
+			self.converted.len(),
+			self.failed.len()
+		)
+	}
+}
+
+fn is_save(path: &Path) -> bool {
+	path.is_file()
+		&& path
+			.extension()
+			.and_then(|extension| extension.to_str())
+			.is_some_and(|extension| extension.eq_ignore_ascii_case(SAVE_EXTENSION))
+}
+
+fn convert_save(save_path: &Path, out_dir: &Path) -> Result<PathBuf> {
+	let json = SaveGame::from_path(save_path)?.to_json()?;
+	let name = save_path.file_stem().context("Save has no file name")?;
+	let out_path = out_dir.join(format!("{}.json", name.to_string_lossy()));
+	let text = serde_json::to_string_pretty(&json)?;
+	std::fs::write(&out_path, text)
+		.with_context(|| format!("Couldn't write {}", out_path.display()))?;
+	Ok(out_path)
+}
+
+// writes {name}.json into out_dir for every .sav in dir, in file name order
+pub fn convert_saves_in_dir(dir: &Path, out_dir: &Path) -> Result<ConversionSummary> {
+	let mut saves = Vec::new();
+	for entry in
+		std::fs::read_dir(dir).with_context(|| format!("Couldn't read {}", dir.display()))?
+	{
+		let path = entry?.path();
+		if is_save(&path) {
+			saves.push(path);
+		}
+	}
+	saves.sort();
+	std::fs::create_dir_all(out_dir)
+		.with_context(|| format!("Couldn't create {}", out_dir.display()))?;
+	let mut summary = ConversionSummary::default();
+	for save in saves {
+		match convert_save(&save, out_dir) {
+			Ok(out_path) => summary.converted.push(out_path),
+			Err(e) => summary.failed.push((save, e)),
+		}
+	}
+	Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::sq::{shared::Writable, sq_value::SQValue};
+	use crate::temp_dir::TempDir;
+
+	#[test]
+	fn convert_folder_of_saves() {
+		let dir = TempDir::new("convert_saves");
+		let saves_dir = dir.join("saves");
+		let out_dir = dir.join("json");
+		std::fs::create_dir_all(&saves_dir).unwrap();
+
+		let mut bytes = Vec::new();
+		SaveGame::from_value(SQValue::Array(vec![SQValue::Int(1)]))
+			.with_name("good")
+			.write_into(&mut bytes)
+			.unwrap();
+		std::fs::write(saves_dir.join("good.sav"), &bytes).unwrap();
+		std::fs::write(saves_dir.join("broken.sav"), b"not a save").unwrap();
+		std::fs::write(saves_dir.join("notes.txt"), b"ignored").unwrap();
+
+		let summary = convert_saves_in_dir(&saves_dir, &out_dir).unwrap();
+		assert_eq!(summary.to_string(), "Converted 1 saves, 1 failed");
+		assert_eq!(summary.converted, vec![out_dir.join("good.json")]);
+		assert_eq!(summary.failed[0].0, saves_dir.join("broken.sav"));
+		let json: serde_json::Value =
+			serde_json::from_str(&std::fs::read_to_string(out_dir.join("good.json")).unwrap())
+				.unwrap();
+		assert_eq!(json["file_name"], "good");
+		assert_eq!(json["content"], serde_json::json!([1]));
+	}
+}
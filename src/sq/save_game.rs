@@ -1,14 +1,15 @@
 use std::{
 	collections::HashMap,
 	io::{Cursor, Read},
+	path::Path,
 };
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use byteorder::{ReadBytesExt, WriteBytesExt};
 use bytes::Buf;
 use chrono::{NaiveDateTime, Timelike};
 
-use crate::sq::serialized_sq_value::SerializedSQValue;
+use crate::sq::serialized_sq_value::{LenientParse, SerializedSQValue};
 
 use super::{
 	shared::{Readable, Writable},
@@ -51,11 +52,9 @@ impl Default for SaveGame {
 	}
 }
 
-impl Readable for SaveGame {
-	fn from_reader<R: Read + ReadBytesExt>(reader: &mut R) -> anyhow::Result<Self>
-	where
-		Self: Sized,
-	{
+impl SaveGame {
+	// everything up to the serialized content, raw_data is left empty
+	fn header_from_reader<R: Read + ReadBytesExt>(reader: &mut R) -> Result<Self> {
 		let magic_num = u16::from_reader(reader)?;
 		let layout_version = u8::from_reader(reader)?;
 		let serialization_version = i32::from_reader(reader)?;
@@ -72,9 +71,6 @@ impl Readable for SaveGame {
 		}
 		let magic_num_2 = u16::from_reader(reader)?;
 
-		let mut raw_data = Vec::new();
-		reader.read_to_end(&mut raw_data)?;
-
 		Ok(Self {
 			magic_num,
 			layout_version,
@@ -84,11 +80,22 @@ impl Readable for SaveGame {
 			file_name,
 			meta_data,
 			magic_num_2,
-			raw_data,
+			raw_data: Vec::new(),
 		})
 	}
 }
 
+impl Readable for SaveGame {
+	fn from_reader<R: Read + ReadBytesExt>(reader: &mut R) -> anyhow::Result<Self>
+	where
+		Self: Sized,
+	{
+		let mut save_game = Self::header_from_reader(reader)?;
+		reader.read_to_end(&mut save_game.raw_data)?;
+		Ok(save_game)
+	}
+}
+
 impl Writable for SaveGame {
 	fn write_into<W: std::io::Write + WriteBytesExt>(&self, writer: &mut W) -> anyhow::Result<()> {
 		self.magic_num.write_into(writer)?;
@@ -114,7 +121,6 @@ impl Writable for SaveGame {
 	}
 }
 
-#[allow(dead_code)] // temporary so clippy doesn't complain
 impl SaveGame {
 	pub fn with_name<S: Into<String>>(mut self, file_name: S) -> Self {
 		self.file_name = file_name.into();
@@ -126,10 +132,35 @@ impl SaveGame {
 		self
 	}
 
+	// the header's key/value pairs BB shows in the load menu, difficulty, ironman and so on
+	pub fn metadata(&self) -> &HashMap<String, String> {
+		&self.meta_data
+	}
+
+	pub fn metadata_mut(&mut self) -> &mut HashMap<String, String> {
+		&mut self.meta_data
+	}
+
+	// one "key: value" line per entry, sorted by key since the map has no stable order
+	pub fn describe_metadata(&self) -> String {
+		let mut entries: Vec<_> = self.meta_data.iter().collect();
+		entries.sort();
+		entries
+			.into_iter()
+			.map(|(key, value)| format!("{}: {}", key, value))
+			.collect::<Vec<_>>()
+			.join("\n")
+	}
+
+	pub fn from_path(path: &Path) -> Result<Self> {
+		let bytes =
+			std::fs::read(path).with_context(|| format!("Couldn't read {}", path.display()))?;
+		Self::from_reader(&mut Cursor::new(bytes))
+	}
+
 	pub fn parse_content(&self) -> Result<SQValue> {
 		let mut reader = Cursor::new(&self.raw_data);
 		let sq_value = SerializedSQValue::from_reader(&mut reader)?;
-		println!("{:?}", sq_value);
 		if reader.has_remaining() {
 			Err(anyhow!("Failed to parse all content"))
 		} else {
@@ -137,15 +168,96 @@ impl SaveGame {
 		}
 	}
 
-	pub fn from_value(value: SQValue) -> Self {
+	// for large saves that only need reading, parses the content straight from the reader
+	// instead of buffering it in raw_data first, which is left empty
+	// the reader should be buffered since the content is read a few bytes at a time
+	pub fn parse_from_reader<R: Read + ReadBytesExt>(reader: &mut R) -> Result<(Self, SQValue)> {
+		let save_game = Self::header_from_reader(reader)?;
+		let sq_value = SerializedSQValue::from_reader(reader)?;
+		if reader.read(&mut [0])? != 0 {
+			return Err(anyhow!("Failed to parse all content"));
+		}
+		Ok((save_game, sq_value.try_into()?))
+	}
+
+	// reads and writes a save back, content included, so real saves can be checked for parser bugs
+	// the whole file has to match byte for byte, and so does the content on its own
+	pub fn check_round_trip(bytes: &[u8]) -> Result<()> {
+		let save_game = Self::from_reader(&mut Cursor::new(bytes))?;
+		let mut written = Vec::new();
+		save_game.write_into(&mut written)?;
+		if let Some(offset) = first_mismatch(bytes, &written) {
+			return Err(anyhow!(
+				"Re-saved file differs from the original at byte {:#X}",
+				offset
+			));
+		}
+
+		let mut reader = Cursor::new(&save_game.raw_data);
+		let content = SerializedSQValue::from_reader(&mut reader)?;
+		if reader.has_remaining() {
+			return Err(anyhow!("Failed to parse all content"));
+		}
+		let mut rewritten = Vec::new();
+		content.write_into(&mut rewritten)?;
+		if let Some(offset) = first_mismatch(&save_game.raw_data, &rewritten) {
+			return Err(anyhow!(
+				"Re-serialized content differs from the original at content byte {:#X}",
+				offset
+			));
+		}
+		Ok(())
+	}
+
+	// the header metadata next to the parsed content, for looking at saves outside the game
+	pub fn to_json(&self) -> Result<serde_json::Value> {
+		Ok(serde_json::json!({
+			"file_name": self.file_name,
+			"metadata": self.meta_data,
+			"content": self.parse_content()?.to_json(),
+		}))
+	}
+
+	// for saves from newer game versions, see SerializedSQValue::from_bytes_lenient
+	pub fn parse_content_lenient(&self) -> Result<LenientParse> {
+		SerializedSQValue::from_bytes_lenient(&self.raw_data)
+	}
+
+	// works on content that can't be converted to an SQValue yet, like a Serialized array
+	pub fn describe(&self) -> String {
+		let mut reader = Cursor::new(&self.raw_data);
+		match SerializedSQValue::from_reader(&mut reader) {
+			Ok(content) if reader.has_remaining() => format!(
+				"{} followed by {} unread bytes",
+				content.describe(),
+				reader.remaining()
+			),
+			Ok(content) => content.describe(),
+			Err(e) => format!("Unreadable content: {}", e),
+		}
+	}
+
+	pub fn with_value(self, value: SQValue) -> Self {
 		let mut raw_data = Vec::new();
 		let mut writer = Cursor::new(&mut raw_data);
 		let serialized = SerializedSQValue::from(value);
 		serialized.write_into(&mut writer).unwrap();
-		Self::default().with_raw_data(raw_data)
+		self.with_raw_data(raw_data)
+	}
+
+	pub fn from_value(value: SQValue) -> Self {
+		Self::default().with_value(value)
 	}
 }
 
+fn first_mismatch(original: &[u8], written: &[u8]) -> Option<usize> {
+	original
+		.iter()
+		.zip(written)
+		.position(|(a, b)| a != b)
+		.or_else(|| (original.len() != written.len()).then_some(original.len().min(written.len())))
+}
+
 #[cfg(test)]
 mod tests {
 	use ordered_float::OrderedFloat;
@@ -180,4 +292,65 @@ mod tests {
 		save_game.file_name = "test".to_owned();
 		test_readable_writable_impls(&save_game);
 	}
+
+	#[test]
+	fn parse_save_game_from_reader() {
+		let value = SQValue::Array(vec![SQValue::String("key".to_owned()), SQValue::Int(300)]);
+		let save_game = SaveGame::from_value(value.clone()).with_name("test");
+		let mut bytes = Vec::new();
+		save_game.write_into(&mut bytes).unwrap();
+
+		let (parsed, parsed_value) = SaveGame::parse_from_reader(&mut Cursor::new(&bytes)).unwrap();
+		assert_eq!(parsed_value, value);
+		assert_eq!(parsed.file_name, save_game.file_name);
+		assert!(parsed.raw_data.is_empty());
+
+		bytes.push(0);
+		assert!(SaveGame::parse_from_reader(&mut Cursor::new(&bytes)).is_err());
+	}
+
+	#[test]
+	fn describe_save_game() {
+		let value = SQValue::Array(vec![SQValue::Null, SQValue::Int(1), SQValue::Bool(false)]);
+		let mut save_game = SaveGame::from_value(value);
+		assert_eq!(save_game.describe(), "Array of 3 elements");
+		save_game.raw_data.push(0);
+		assert_eq!(
+			save_game.describe(),
+			"Array of 3 elements followed by 1 unread bytes"
+		);
+		assert!(SaveGame::default()
+			.describe()
+			.starts_with("Unreadable content"));
+	}
+
+	#[test]
+	fn edit_metadata() {
+		let mut save_game = SaveGame::default();
+		assert!(save_game.metadata().is_empty());
+		assert_eq!(save_game.describe_metadata(), "");
+		let metadata = save_game.metadata_mut();
+		metadata.insert("ironman".to_owned(), "1".to_owned());
+		metadata.insert("difficulty".to_owned(), "2".to_owned());
+		assert_eq!(save_game.describe_metadata(), "difficulty: 2\nironman: 1");
+
+		let mut bytes = Vec::new();
+		save_game.write_into(&mut bytes).unwrap();
+		let read = SaveGame::from_reader(&mut Cursor::new(&bytes)).unwrap();
+		assert_eq!(read.metadata(), save_game.metadata());
+	}
+
+	#[test]
+	fn check_save_game_round_trip() {
+		let value = SQValue::Array(vec![SQValue::String("key".to_owned()), SQValue::Int(-300)]);
+		let mut bytes = Vec::new();
+		SaveGame::from_value(value)
+			.with_name("test")
+			.write_into(&mut bytes)
+			.unwrap();
+		SaveGame::check_round_trip(&bytes).unwrap();
+
+		bytes.push(0);
+		assert!(SaveGame::check_round_trip(&bytes).is_err());
+	}
 }
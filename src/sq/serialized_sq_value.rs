@@ -1,4 +1,4 @@
-use std::io::{Read, Write};
+use std::io::{Cursor, Read, Write};
 
 use anyhow::{anyhow, Result};
 use byteorder::{ReadBytesExt, WriteBytesExt};
@@ -87,6 +87,154 @@ impl SerializedSQValue {
 	}
 }
 
+impl SerializedSQValue {
+	fn type_name(&self) -> &'static str {
+		match self {
+			Self::None => "None",
+			Self::Unknown => "Unknown",
+			Self::Null => "Null",
+			Self::Bool(_) => "Bool",
+			Self::String(_) => "String",
+			Self::U8(_) => "U8",
+			Self::U16(_) => "U16",
+			Self::U32(_) => "U32",
+			Self::I8(_) => "I8",
+			Self::I16(_) => "I16",
+			Self::I32(_) => "I32",
+			Self::Float(_) => "Float",
+			Self::Table(_) => "Table",
+			Self::Array(_) => "Array",
+			Self::Serialized(_, _) => "Serialized array",
+		}
+	}
+
+	// only the outermost value, e.g. "Serialized array of 3 elements, meta 'WorldState'"
+	pub fn describe(&self) -> String {
+		match self {
+			Self::Table(table) => format!("{} of {} entries", self.type_name(), table.len()),
+			Self::Array(array) => format!("{} of {} elements", self.type_name(), array.len()),
+			Self::Serialized(array, meta_data) => format!(
+				"{} of {} elements, meta '{}'",
+				self.type_name(),
+				array.len(),
+				meta_data.name
+			),
+			_ => self.type_name().to_owned(),
+		}
+	}
+}
+
+// where a lenient parse stopped, so new save formats can be worked out from user-submitted files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsupportedType {
+	pub offset: u64,
+	pub type_byte: u8,
+}
+
+impl std::fmt::Display for UnsupportedType {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"Unsupported type byte {:#04X} at offset {:#X}",
+			self.type_byte, self.offset
+		)
+	}
+}
+
+#[derive(Debug)]
+pub struct LenientParse {
+	// None when the very first type byte is unsupported
+	pub value: Option<SerializedSQValue>,
+	pub unsupported: Option<UnsupportedType>,
+}
+
+impl SerializedSQValue {
+	// unlike from_reader, an unknown type byte ends the parse with what was read so far
+	// anything else that's invalid, like running out of bytes, is still an error
+	pub fn from_bytes_lenient(bytes: &[u8]) -> Result<LenientParse> {
+		let mut reader = Cursor::new(bytes);
+		let mut unsupported = None;
+		let value = Self::read_lenient(&mut reader, &mut unsupported)?;
+		Ok(LenientParse { value, unsupported })
+	}
+
+	// containers keep the elements read before the unsupported one
+	fn read_lenient(
+		reader: &mut Cursor<&[u8]>,
+		unsupported: &mut Option<UnsupportedType>,
+	) -> Result<Option<Self>> {
+		let offset = reader.position();
+		let sq_type = u8::from_reader(reader)?;
+		Ok(Some(match sq_type {
+			0..=11 => {
+				reader.set_position(offset);
+				Self::from_reader(reader)?
+			}
+			12 => {
+				let mut table = Vec::new();
+				for _ in 0..Self::read_len(reader)? {
+					let Some(key) = Self::read_lenient(reader, unsupported)? else {
+						break;
+					};
+					let Some(value) = Self::read_lenient(reader, unsupported)? else {
+						break;
+					};
+					table.push((key, value));
+					if unsupported.is_some() {
+						break;
+					}
+				}
+				Self::Table(table)
+			}
+			13 => Self::Array(Self::read_array_lenient(reader, unsupported)?),
+			14 => {
+				let array = Self::read_array_lenient(reader, unsupported)?;
+				// a Serialized array cut short never got to its metadata, so it's kept as a plain Array
+				if unsupported.is_some() {
+					Self::Array(array)
+				} else {
+					Self::Serialized(array, MetaDataEmulator::from_reader(reader)?)
+				}
+			}
+			_ => {
+				*unsupported = Some(UnsupportedType {
+					offset,
+					type_byte: sq_type,
+				});
+				return Ok(None);
+			}
+		}))
+	}
+
+	fn read_array_lenient(
+		reader: &mut Cursor<&[u8]>,
+		unsupported: &mut Option<UnsupportedType>,
+	) -> Result<Vec<Self>> {
+		let mut array = Vec::new();
+		for _ in 0..Self::read_len(reader)? {
+			let Some(value) = Self::read_lenient(reader, unsupported)? else {
+				break;
+			};
+			array.push(value);
+			if unsupported.is_some() {
+				break;
+			}
+		}
+		Ok(array)
+	}
+
+	// the same length encoding Vec::from_reader expects
+	fn read_len(reader: &mut Cursor<&[u8]>) -> Result<i32> {
+		match SQValue::try_from(Self::from_reader(reader)?)? {
+			SQValue::Int(len) => Ok(len),
+			len => Err(anyhow!(
+				"Invalid SerializedSQValue for collection length {:?}",
+				len
+			)),
+		}
+	}
+}
+
 impl Readable for SerializedSQValue {
 	fn from_reader<R: Read + ReadBytesExt>(reader: &mut R) -> Result<Self>
 	where
@@ -113,7 +261,7 @@ impl Readable for SerializedSQValue {
 				let meta_data = MetaDataEmulator::from_reader(reader)?;
 				Ok(Self::Serialized(array, meta_data))
 			}
-			_ => Err(anyhow!("Invalid SerializedSQValue")),
+			_ => Err(anyhow!("Invalid SerializedSQValue type {}", sq_type)),
 		}
 	}
 }
@@ -159,6 +307,8 @@ impl From<SQValue> for SerializedSQValue {
 			SQValue::Null => SerializedSQValue::Null,
 			SQValue::Bool(bool) => SerializedSQValue::Bool(bool),
 			SQValue::String(string) => SerializedSQValue::String(string),
+			// the ranges share their endpoints and the first matching arm wins,
+			// so e.g. -128 is written as an I16 and 0 as an I8, which still read back unchanged
 			SQValue::Int(int) => match int {
 				i32::MIN..=I16_MIN => SerializedSQValue::I32(int),
 				I16_MIN..=I8_MIN => SerializedSQValue::I16(int as i16),
@@ -186,6 +336,7 @@ impl From<SQValue> for SerializedSQValue {
 mod tests {
 	use ordered_float::OrderedFloat;
 
+	use crate::sq::save_game::SaveGame;
 	use crate::sq::shared::test_readable_writable_impls;
 
 	use super::*;
@@ -214,6 +365,114 @@ mod tests {
 		test_readable_writable_impls(&meta_data);
 	}
 
+	#[test]
+	fn serialized_in_save_game() {
+		let meta_data = MetaDataEmulator {
+			version: 1,
+			name: "WorldState".to_owned(),
+			file_name: "test".to_owned(),
+			creation_date: chrono::Local::now().to_rfc3339(),
+			modification_date: chrono::Local::now().to_rfc3339(),
+			meta_data: Box::new(SerializedSQValue::Array(vec![SerializedSQValue::Table(
+				vec![(
+					SerializedSQValue::String("key".to_owned()),
+					SerializedSQValue::String("value".to_owned()),
+				)],
+			)])),
+		};
+		let value = SerializedSQValue::Serialized(
+			vec![
+				SerializedSQValue::String("item".to_owned()),
+				SerializedSQValue::I32(-100_000),
+				SerializedSQValue::Float(OrderedFloat(0.5)),
+				SerializedSQValue::Array(vec![SerializedSQValue::Bool(true)]),
+			],
+			meta_data,
+		);
+		let mut raw_data = Vec::new();
+		value.write_into(&mut raw_data).unwrap();
+		let save_game = SaveGame::default()
+			.with_name("test")
+			.with_raw_data(raw_data);
+		test_readable_writable_impls(&save_game);
+
+		let mut bytes = Vec::new();
+		save_game.write_into(&mut bytes).unwrap();
+		SaveGame::check_round_trip(&bytes).unwrap();
+		let read_back = SaveGame::from_reader(&mut Cursor::new(&bytes)).unwrap();
+		let read_value =
+			SerializedSQValue::from_reader(&mut Cursor::new(&read_back.raw_data)).unwrap();
+		assert_eq!(read_value, value);
+	}
+
+	#[test]
+	fn describe_serialized() {
+		let meta_data = MetaDataEmulator {
+			version: 1,
+			name: "WorldState".to_owned(),
+			file_name: String::new(),
+			creation_date: String::new(),
+			modification_date: String::new(),
+			meta_data: Box::new(SerializedSQValue::Array(vec![])),
+		};
+		let value = SerializedSQValue::Serialized(
+			vec![
+				SerializedSQValue::Null,
+				SerializedSQValue::U8(1),
+				SerializedSQValue::Table(vec![]),
+			],
+			meta_data,
+		);
+		assert_eq!(
+			value.describe(),
+			"Serialized array of 3 elements, meta 'WorldState'"
+		);
+		assert_eq!(SerializedSQValue::I16(-2).describe(), "I16");
+	}
+
+	#[test]
+	fn lenient_parse_stops_at_unsupported_type() {
+		let value = SerializedSQValue::Array(vec![
+			SerializedSQValue::I32(7),
+			SerializedSQValue::Table(vec![(
+				SerializedSQValue::String("key".to_owned()),
+				SerializedSQValue::Bool(true),
+			)]),
+			SerializedSQValue::Null,
+		]);
+		let mut bytes = Vec::new();
+		value.write_into(&mut bytes).unwrap();
+		let parsed = SerializedSQValue::from_bytes_lenient(&bytes).unwrap();
+		assert_eq!(parsed.value, Some(value));
+		assert!(parsed.unsupported.is_none());
+
+		// the trailing Null becomes a type this parser doesn't know about
+		let offset = bytes.len() - 1;
+		bytes[offset] = 0x20;
+		assert!(SerializedSQValue::from_reader(&mut Cursor::new(&bytes)).is_err());
+		let parsed = SerializedSQValue::from_bytes_lenient(&bytes).unwrap();
+		let Some(SerializedSQValue::Array(array)) = parsed.value else {
+			panic!("Expected the partial array");
+		};
+		assert_eq!(array.len(), 2);
+		let unsupported = parsed.unsupported.unwrap();
+		assert_eq!(
+			unsupported,
+			UnsupportedType {
+				offset: offset as u64,
+				type_byte: 0x20
+			}
+		);
+		assert_eq!(
+			unsupported.to_string(),
+			format!("Unsupported type byte 0x20 at offset {:#X}", offset)
+		);
+		assert!(SerializedSQValue::from_bytes_lenient(&[0x20])
+			.unwrap()
+			.value
+			.is_none());
+	}
+
 	#[test]
 	fn read_write_serialized_sq_value() {
 		let serialized_sq_value = SerializedSQValue::Array(vec![
@@ -236,4 +495,33 @@ mod tests {
 		]);
 		test_readable_writable_impls(&serialized_sq_value);
 	}
+
+	#[test]
+	fn int_boundaries_round_trip() {
+		for int in [
+			i32::MIN,
+			I16_MIN - 1,
+			I16_MIN,
+			I16_MIN + 1,
+			-129,
+			-128,
+			-127,
+			-1,
+			0,
+			1,
+			255,
+			256,
+			65535,
+			65536,
+			i32::MAX,
+		] {
+			let serialized = SerializedSQValue::from(SQValue::Int(int));
+			let mut buf = Vec::new();
+			serialized.write_into(&mut buf).unwrap();
+			let read = SerializedSQValue::from_reader(&mut std::io::Cursor::new(buf)).unwrap();
+			assert_eq!(read, serialized);
+			let value: SQValue = read.try_into().unwrap();
+			assert_eq!(value, SQValue::Int(int), "{} didn't round trip", int);
+		}
+	}
 }
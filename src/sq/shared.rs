@@ -31,7 +31,7 @@ impl Readable for String {
 		let len = reader.read_u16::<LittleEndian>()?;
 		let mut buf = vec![0; len.into()];
 		reader.read_exact(&mut buf)?;
-		Ok(String::from_utf8(buf).unwrap())
+		Ok(String::from_utf8(buf)?)
 	}
 }
 
@@ -47,7 +47,10 @@ impl Writable for NaiveDateTime {
 impl Readable for NaiveDateTime {
 	fn from_reader<R: Read + ReadBytesExt>(reader: &mut R) -> Result<Self> {
 		let timestamp = reader.read_i64::<LittleEndian>()?;
-		Ok(DateTime::from_timestamp(timestamp, 0).unwrap().naive_utc())
+		// a corrupt save shouldn't panic a batch of them
+		DateTime::from_timestamp(timestamp, 0)
+			.map(|date| date.naive_utc())
+			.ok_or_else(|| anyhow!("Invalid timestamp {}", timestamp))
 	}
 }
 
@@ -49,6 +49,104 @@ pub enum SQValue {
 	Array(Vec<SQValue>),
 }
 
+fn display_path(path: &[&str]) -> String {
+	if path.is_empty() {
+		"The root value".to_owned()
+	} else {
+		path.join(".")
+	}
+}
+
+impl SQValue {
+	pub fn get(&self, key: &str) -> Option<&SQValue> {
+		self.as_table()?.0.get(&SQValue::String(key.to_owned()))
+	}
+
+	pub fn get_index(&self, index: usize) -> Option<&SQValue> {
+		self.as_array()?.get(index)
+	}
+
+	pub fn as_int(&self) -> Option<i32> {
+		match self {
+			Self::Int(int) => Some(*int),
+			_ => None,
+		}
+	}
+
+	pub fn as_str(&self) -> Option<&str> {
+		match self {
+			Self::String(string) => Some(string),
+			_ => None,
+		}
+	}
+
+	pub fn as_array(&self) -> Option<&Vec<SQValue>> {
+		match self {
+			Self::Array(array) => Some(array),
+			_ => None,
+		}
+	}
+
+	pub fn as_table(&self) -> Option<&SQTable> {
+		match self {
+			Self::Table(table) => Some(table),
+			_ => None,
+		}
+	}
+
+	// tables become objects, so keys that aren't strings are written as their string form
+	// NaN and infinite floats have no JSON representation and become null
+	pub fn to_json(&self) -> serde_json::Value {
+		match self {
+			Self::Null => serde_json::Value::Null,
+			Self::Bool(b) => serde_json::Value::Bool(*b),
+			Self::String(s) => serde_json::Value::String(s.clone()),
+			Self::Int(i) => serde_json::Value::from(*i),
+			Self::Float(f) => serde_json::Number::from_f64(f.into_inner().into())
+				.map_or(serde_json::Value::Null, serde_json::Value::Number),
+			Self::Table(table) => serde_json::Value::Object(
+				table
+					.0
+					.iter()
+					.map(|(key, value)| (key.to_json_key(), value.to_json()))
+					.collect(),
+			),
+			Self::Array(array) => {
+				serde_json::Value::Array(array.iter().map(SQValue::to_json).collect())
+			}
+		}
+	}
+
+	fn to_json_key(&self) -> String {
+		match self {
+			Self::String(s) => s.clone(),
+			other => other.to_json().to_string(),
+		}
+	}
+
+	// replaces the value at the end of the path, inserting it if the last key is missing
+	pub fn set_path(&mut self, path: &[&str], value: SQValue) -> Result<()> {
+		let (leaf, intermediate) = path
+			.split_last()
+			.ok_or_else(|| anyhow!("Tried to set an empty path"))?;
+		let mut current = self;
+		for (i, key) in intermediate.iter().enumerate() {
+			let Self::Table(table) = current else {
+				return Err(anyhow!("{} is not a table", display_path(&path[..i])));
+			};
+			current = table
+				.0
+				.get_mut(&SQValue::String((*key).to_owned()))
+				.ok_or_else(|| anyhow!("{} doesn't exist", path[..=i].join(".")))?;
+		}
+		let Self::Table(table) = current else {
+			return Err(anyhow!("{} is not a table", display_path(intermediate)));
+		};
+		table.0.insert(SQValue::String((*leaf).to_owned()), value);
+		Ok(())
+	}
+}
+
 impl TryFrom<SerializedSQValue> for SQValue {
 	type Error = anyhow::Error;
 
@@ -115,4 +213,149 @@ mod tests {
 		let deserialized_value: SQValue = serialized_value.try_into().unwrap();
 		assert_eq!(deserialized_value, value);
 	}
+
+	#[test]
+	fn accessors() {
+		let value = SQValue::Table(SQTable(
+			vec![
+				(
+					SQValue::String("name".to_owned()),
+					SQValue::String("Hoggart".to_owned()),
+				),
+				(
+					SQValue::String("roster".to_owned()),
+					SQValue::Array(vec![
+						SQValue::Table(SQTable(
+							vec![(SQValue::String("level".to_owned()), SQValue::Int(11))]
+								.into_iter()
+								.collect(),
+						)),
+						SQValue::Null,
+					]),
+				),
+			]
+			.into_iter()
+			.collect(),
+		));
+
+		assert_eq!(value.get("name").and_then(SQValue::as_str), Some("Hoggart"));
+		assert_eq!(
+			value
+				.get("roster")
+				.and_then(|roster| roster.get_index(0))
+				.and_then(|bro| bro.get("level"))
+				.and_then(SQValue::as_int),
+			Some(11)
+		);
+		assert_eq!(
+			value
+				.get("roster")
+				.and_then(SQValue::as_array)
+				.map(Vec::len),
+			Some(2)
+		);
+		assert_eq!(
+			value.get("roster").and_then(|roster| roster.get_index(1)),
+			Some(&SQValue::Null)
+		);
+		assert!(value.as_table().is_some());
+
+		assert!(value.get("missing").is_none());
+		assert!(value
+			.get("roster")
+			.and_then(|roster| roster.get_index(2))
+			.is_none());
+		assert!(value.get("name").and_then(SQValue::as_int).is_none());
+		assert!(value.get_index(0).is_none());
+		assert!(SQValue::Int(1).get("name").is_none());
+	}
+
+	fn nested_table() -> SQValue {
+		SQValue::Table(SQTable(
+			vec![(
+				SQValue::String("world".to_owned()),
+				SQValue::Table(SQTable(
+					vec![
+						(SQValue::String("day".to_owned()), SQValue::Int(3)),
+						(SQValue::String("roster".to_owned()), SQValue::Array(vec![])),
+					]
+					.into_iter()
+					.collect(),
+				)),
+			)]
+			.into_iter()
+			.collect(),
+		))
+	}
+
+	#[test]
+	fn set_path_existing_leaf() {
+		let mut value = nested_table();
+		value
+			.set_path(&["world", "day"], SQValue::Int(100))
+			.unwrap();
+		assert_eq!(
+			value.get("world").and_then(|world| world.get("day")),
+			Some(&SQValue::Int(100))
+		);
+		assert_eq!(
+			value
+				.get("world")
+				.and_then(SQValue::as_table)
+				.map(|t| t.0.len()),
+			Some(2)
+		);
+	}
+
+	#[test]
+	fn set_path_new_leaf() {
+		let mut value = nested_table();
+		value
+			.set_path(&["world", "seed"], SQValue::String("abc".to_owned()))
+			.unwrap();
+		assert_eq!(
+			value
+				.get("world")
+				.and_then(|world| world.get("seed"))
+				.and_then(SQValue::as_str),
+			Some("abc")
+		);
+		value.set_path(&["top"], SQValue::Bool(true)).unwrap();
+		assert_eq!(value.get("top"), Some(&SQValue::Bool(true)));
+	}
+
+	#[test]
+	fn json_export() {
+		let value = SQValue::Table(SQTable(
+			vec![
+				(
+					SQValue::String("roster".to_owned()),
+					SQValue::Array(vec![SQValue::Int(1), SQValue::Null, SQValue::Bool(true)]),
+				),
+				(SQValue::Int(7), SQValue::Float(OrderedFloat(0.5))),
+				(
+					SQValue::String("nan".to_owned()),
+					SQValue::Float(OrderedFloat(f32::NAN)),
+				),
+			]
+			.into_iter()
+			.collect(),
+		));
+		assert_eq!(
+			value.to_json().to_string(),
+			r#"{"7":0.5,"nan":null,"roster":[1,null,true]}"#
+		);
+	}
+
+	#[test]
+	fn set_path_errors() {
+		let mut value = nested_table();
+		assert!(value.set_path(&[], SQValue::Null).is_err());
+		assert!(value
+			.set_path(&["world", "roster", "x"], SQValue::Null)
+			.is_err());
+		assert!(value.set_path(&["missing", "x"], SQValue::Null).is_err());
+		assert!(SQValue::Int(1).set_path(&["x"], SQValue::Null).is_err());
+		assert_eq!(value, nested_table());
+	}
 }
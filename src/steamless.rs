@@ -1,28 +1,45 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use bytes::Bytes;
 use dioxus::signals::{Readable, SyncSignal, Writable};
+use reqwest::{header::RANGE, StatusCode};
 use sha2::{Digest, Sha256};
 use std::{
 	fs::File,
 	io::{Cursor, Read, Write},
-	path::Path,
+	path::{Path, PathBuf},
+	time::Duration,
 };
 use zip::ZipArchive;
 
-use crate::config::Config;
+use crate::config::{self, Config};
+use crate::log::{self, Operation};
 
 const STEAMLESS_CLI: &str = "Steamless.CLI.exe";
 const STEAMLESS_PLUGIN_FOLDER: &str = "Plugins";
 const STEAMLESS_API_NAME: &str = "Steamless.API.dll";
-const STEAMLESS_31_X86_VARIANT_NAME: &str = "Steamless.Unpacker.Variant31.x86.dll";
-
+// the x64 unpacker is only needed for 64-bit exes, but it's small enough to always install
+const REQUIRED_PLUGINS: [&str; 3] = [
+	STEAMLESS_API_NAME,
+	"Steamless.Unpacker.Variant31.x86.dll",
+	"Steamless.Unpacker.Variant31.x64.dll",
+];
+pub const STEAMLESS_VERSION: &str = "v3.1.0.5";
 pub const ZIP_URL: &str = "https://github.com/atom0s/Steamless/releases/download/v3.1.0.5/Steamless.v3.1.0.5.-.by.atom0s.zip";
-const STEAMLESS_HASH: [u8; 32] = match const_hex::const_decode_to_array(
+pub const STEAMLESS_HASH: [u8; 32] = match const_hex::const_decode_to_array(
 	b"E3E2D22E098FF3FB359B2876AA2BED9596F0501E6FF588CBFFAE90A76D2DC4F5",
 ) {
 	Ok(array) => array,
 	Err(_) => unreachable!(),
 };
+// each file as extracted from the release zip above, so an install that predates these checks
+// or was copied over by hand is recognised without downloading it again
+// TODO: fill in with the sha256sum of each file in the v3.1.0.5 zip
+const STEAMLESS_FILE_HASHES: [(&str, &str); 4] = [
+	(STEAMLESS_CLI, ""),
+	(STEAMLESS_API_NAME, ""),
+	("Steamless.Unpacker.Variant31.x86.dll", ""),
+	("Steamless.Unpacker.Variant31.x64.dll", ""),
+];
 
 fn extract_file_to_path(
 	zip: &mut ZipArchive<Cursor<Bytes>>,
@@ -41,8 +58,84 @@ fn extract_file_to_path(
 	Ok(())
 }
 
-async fn download_steamless(url: &str, target_path: &Path) -> Result<()> {
-	let response = reqwest::get(url).await?.bytes().await?;
+const DOWNLOAD_ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+// nothing is written to disk until the whole zip is downloaded, so a cancel leaves no partial files
+// appends to bytes, so a retry picks up where the previous attempt stopped if the server supports ranges
+async fn download_chunks(
+	client: &reqwest::Client,
+	url: &str,
+	bytes: &mut Vec<u8>,
+	operation: &Operation,
+) -> Result<()> {
+	let mut request = client.get(url);
+	if !bytes.is_empty() {
+		request = request.header(RANGE, format!("bytes={}-", bytes.len()));
+	}
+	let mut response = request.send().await?.error_for_status()?;
+	if response.status() != StatusCode::PARTIAL_CONTENT {
+		// the range was ignored and the whole file is being sent again
+		bytes.clear();
+	}
+	let total = response
+		.content_length()
+		.map(|length| length + bytes.len() as u64);
+	let mut last_percent = None;
+	while let Some(chunk) = response.chunk().await? {
+		operation.check_cancelled()?;
+		bytes.extend_from_slice(&chunk);
+		if let Some(total) = total.filter(|&total| total > 0) {
+			let percent = bytes.len() as u64 * 100 / total;
+			if last_percent != Some(percent) {
+				last_percent = Some(percent);
+				operation.update(format!("Downloading Steamless... {}%", percent));
+				operation.set_progress(percent as f32 / 100.0);
+			}
+		}
+	}
+	Ok(())
+}
+
+async fn download_with_progress(url: &str, operation: &Operation) -> Result<Bytes> {
+	let client = reqwest::Client::new();
+	let mut bytes = Vec::new();
+	let mut attempt = 1;
+	loop {
+		match download_chunks(&client, url, &mut bytes, operation).await {
+			Ok(()) => return Ok(bytes.into()),
+			Err(e) if attempt < DOWNLOAD_ATTEMPTS && !log::is_cancelled(&e) => {
+				tracing::warn!(
+					"Steamless download attempt {}/{} failed, retrying: {}",
+					attempt,
+					DOWNLOAD_ATTEMPTS,
+					e
+				);
+				attempt += 1;
+				tokio::time::sleep(RETRY_DELAY).await;
+			}
+			Err(e) => return Err(e),
+		}
+	}
+}
+
+// a launcher in Program Files can't write next to itself without elevation,
+// which would otherwise only fail halfway through extracting
+fn check_writable(path: &Path) -> Result<()> {
+	std::fs::create_dir_all(path)
+		.and_then(|_| config::check_writable(path))
+		.with_context(|| {
+			format!(
+				"Can't write to {}; move the launcher out of a protected folder or run it as administrator",
+				path.display()
+			)
+		})
+}
+
+pub async fn download_steamless(url: &str, target_path: &Path) -> Result<()> {
+	check_writable(target_path)?;
+	let operation = Operation::start_cancellable("Downloading Steamless...");
+	let response = download_with_progress(url, &operation).await?;
 	let hash = <Sha256 as Digest>::digest(response.as_ref());
 	if hash.as_slice() != STEAMLESS_HASH {
 		return Err(anyhow!(
@@ -52,26 +145,27 @@ async fn download_steamless(url: &str, target_path: &Path) -> Result<()> {
 
 	let reader = Cursor::new(response);
 	let mut zip = zip::ZipArchive::new(reader)?;
-	let plugins_folder = Path::new(STEAMLESS_PLUGIN_FOLDER);
-	extract_file_to_path(&mut zip, Path::new(STEAMLESS_CLI), target_path)?;
-	extract_file_to_path(
-		&mut zip,
-		&plugins_folder.join(STEAMLESS_API_NAME),
-		target_path,
-	)?;
-	extract_file_to_path(
-		&mut zip,
-		&plugins_folder.join(STEAMLESS_31_X86_VARIANT_NAME),
-		target_path,
-	)?;
+	for file in steamless_files() {
+		extract_file_to_path(&mut zip, &file, target_path)?;
+	}
 	Ok(())
 }
 
 async fn download_steamless_from_config(mut config: SyncSignal<Config>) -> Result<()> {
 	let path = config.with(|c| c.get_steamless_path().to_owned());
+	// a partial or tampered install gets replaced, so say why rather than silently downloading again
+	if let Err(e) = verify_steamless_install(&path) {
+		if missing_steamless_files(&path).len() < steamless_files().len() {
+			tracing::warn!("Reinstalling Steamless: {}", e);
+		}
+	}
 	let result = download_steamless(ZIP_URL, &path).await;
 	if let Err(e) = result {
-		tracing::error!("Failed to download steamless: {}", e);
+		if log::is_cancelled(&e) {
+			tracing::info!("Cancelled the Steamless download");
+		} else {
+			tracing::error!("Failed to download steamless: {}", e);
+		}
 		Err(e)
 	} else {
 		let info = "Successfully installed steamless, ready to apply 4GB patch";
@@ -90,9 +184,187 @@ pub async fn mt_download_steamless_from_config(config: SyncSignal<Config>) {
 	.await;
 }
 
+// relative to the Steamless folder
+pub fn steamless_files() -> Vec<PathBuf> {
+	let plugins_folder = Path::new(STEAMLESS_PLUGIN_FOLDER);
+	std::iter::once(PathBuf::from(STEAMLESS_CLI))
+		.chain(
+			REQUIRED_PLUGINS
+				.iter()
+				.map(|plugin| plugins_folder.join(plugin)),
+		)
+		.collect()
+}
+
+fn sha_hash_file(path: &Path) -> Result<[u8; 32]> {
+	let mut file = File::open(path).with_context(|| format!("Couldn't open {:?}", path))?;
+	let mut hasher = Sha256::new();
+	std::io::copy(&mut file, &mut hasher)?;
+	Ok(hasher.finalize().into())
+}
+
+// relative to the Steamless folder
+pub fn missing_steamless_files(path: &Path) -> Vec<PathBuf> {
+	steamless_files()
+		.into_iter()
+		.filter(|file| !path.join(file).exists())
+		.collect()
+}
+
+pub fn verify_steamless_install(path: &Path) -> Result<()> {
+	verify_files(path, &STEAMLESS_FILE_HASHES)
+}
+
+// expected_hashes is keyed by file name, which is unique across the Steamless files
+fn verify_files(path: &Path, expected_hashes: &[(&str, &str)]) -> Result<()> {
+	let missing = missing_steamless_files(path);
+	if missing.len() == steamless_files().len() {
+		return Err(anyhow!("Steamless isn't installed"));
+	}
+	if !missing.is_empty() {
+		let names: Vec<_> = missing
+			.iter()
+			.map(|file| file.display().to_string())
+			.collect();
+		return Err(anyhow!(
+			"Steamless install is incomplete, missing {}",
+			names.join(", ")
+		));
+	}
+	for file in steamless_files() {
+		let (_, expected_hash) = expected_hashes
+			.iter()
+			.find(|(name, _)| file.file_name() == Some(name.as_ref()))
+			.with_context(|| format!("No known hash for {}", file.display()))?;
+		let file_path = path.join(&file);
+		if !const_hex::encode(sha_hash_file(&file_path)?).eq_ignore_ascii_case(expected_hash) {
+			return Err(anyhow!(
+				"{} doesn't match the Steamless release",
+				file.display()
+			));
+		}
+	}
+	Ok(())
+}
+
+// files that are already gone are skipped, so a partially deleted install can still be cleaned up
+pub fn remove_steamless_files(path: &Path) -> Result<()> {
+	for file in steamless_files() {
+		let file_path = path.join(file);
+		match std::fs::remove_file(&file_path) {
+			Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+				return Err(e).with_context(|| format!("Couldn't remove {:?}", file_path));
+			}
+			_ => {}
+		}
+	}
+	Ok(())
+}
+
 pub fn is_steamless_installed(path: &Path) -> bool {
-	let plugins_folder = path.join(STEAMLESS_PLUGIN_FOLDER);
-	path.join(STEAMLESS_CLI).exists()
-		&& plugins_folder.join(STEAMLESS_API_NAME).exists()
-		&& plugins_folder.join(STEAMLESS_31_X86_VARIANT_NAME).exists()
+	verify_steamless_install(path).is_ok()
+}
+
+// running a missing CLI only fails with an opaque "program not found"
+pub fn cli_path(path: &Path) -> Result<PathBuf> {
+	verify_steamless_install(path).with_context(|| {
+		format!(
+			"Steamless isn't installed in {} — click the 4GB Patcher button to install it",
+			path.display()
+		)
+	})?;
+	Ok(path.join(STEAMLESS_CLI))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::temp_dir::TempDir;
+
+	// an empty or truncated hash would make every install fail verification
+	#[test]
+	fn release_hashes_are_complete() {
+		for (name, hash) in STEAMLESS_FILE_HASHES {
+			let hash = const_hex::decode(hash).unwrap_or_default();
+			assert_eq!(hash.len(), 32, "missing hash for {}", name);
+		}
+	}
+
+	#[test]
+	fn missing_cli_is_reported() {
+		let dir = TempDir::new("steamless_cli");
+		let error = cli_path(&dir).unwrap_err().to_string();
+		assert!(error.contains("click the 4GB Patcher button"), "{}", error);
+	}
+
+	#[test]
+	fn verify_install() {
+		let dir = TempDir::new("steamless");
+		std::fs::create_dir_all(dir.join(STEAMLESS_PLUGIN_FOLDER)).unwrap();
+		for file in steamless_files() {
+			std::fs::write(dir.join(&file), file.to_string_lossy().as_bytes()).unwrap();
+		}
+		let hashes: Vec<_> = steamless_files()
+			.iter()
+			.map(|file| {
+				let name = file.file_name().unwrap().to_str().unwrap();
+				(
+					name,
+					const_hex::encode_upper(sha_hash_file(&dir.join(file)).unwrap()),
+				)
+			})
+			.collect();
+		let hashes: Vec<_> = hashes
+			.iter()
+			.map(|(name, hash)| (*name, hash.as_str()))
+			.collect();
+		verify_files(&dir, &hashes).unwrap();
+
+		let api_path = Path::new(STEAMLESS_PLUGIN_FOLDER).join(STEAMLESS_API_NAME);
+		std::fs::write(dir.join(&api_path), b"tampered").unwrap();
+		let error = verify_files(&dir, &hashes).unwrap_err().to_string();
+		assert!(error.contains(STEAMLESS_API_NAME));
+
+		std::fs::remove_file(dir.join(STEAMLESS_CLI)).unwrap();
+		assert_eq!(
+			missing_steamless_files(&dir),
+			vec![PathBuf::from(STEAMLESS_CLI)]
+		);
+		let error = verify_files(&dir, &hashes).unwrap_err().to_string();
+		assert!(error.contains("missing"));
+		assert!(error.contains(STEAMLESS_CLI));
+	}
+
+	#[test]
+	fn unwritable_path_is_reported() {
+		let dir = TempDir::new("steamless_writable");
+		check_writable(&dir.join("steamless")).unwrap();
+		assert!(!dir
+			.join("steamless")
+			.join(config::WRITE_CHECK_FILE)
+			.exists());
+
+		// a file where a parent folder should be can't be created through, even with elevation
+		std::fs::write(dir.join("blocked"), b"").unwrap();
+		let error = check_writable(&dir.join("blocked").join("steamless")).unwrap_err();
+		assert!(error.to_string().starts_with("Can't write to"));
+	}
+
+	#[test]
+	fn remove_partial_install() {
+		let dir = TempDir::new("steamless_remove");
+		std::fs::create_dir_all(dir.join(STEAMLESS_PLUGIN_FOLDER)).unwrap();
+		std::fs::write(dir.join(STEAMLESS_CLI), b"cli").unwrap();
+		assert!(verify_steamless_install(&dir)
+			.unwrap_err()
+			.to_string()
+			.contains("incomplete"));
+		remove_steamless_files(&dir).unwrap();
+		assert_eq!(
+			verify_steamless_install(&dir).unwrap_err().to_string(),
+			"Steamless isn't installed"
+		);
+		assert!(!dir.join(STEAMLESS_CLI).exists());
+		remove_steamless_files(&dir).unwrap();
+	}
 }
@@ -0,0 +1,36 @@
+use std::path::{Path, PathBuf};
+
+// a scratch folder for a test, removed again even when the test fails
+// the process id keeps the folders of two test runs at the same time apart
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+	// the name only has to be unique among the tests, they run in parallel
+	pub fn new(name: &str) -> Self {
+		let path =
+			std::env::temp_dir().join(format!("msu_launcher_test_{}_{}", name, std::process::id()));
+		let _ = std::fs::remove_dir_all(&path);
+		std::fs::create_dir_all(&path).unwrap();
+		Self(path)
+	}
+}
+
+impl std::ops::Deref for TempDir {
+	type Target = Path;
+
+	fn deref(&self) -> &Path {
+		&self.0
+	}
+}
+
+impl AsRef<Path> for TempDir {
+	fn as_ref(&self) -> &Path {
+		&self.0
+	}
+}
+
+impl Drop for TempDir {
+	fn drop(&mut self) {
+		let _ = std::fs::remove_dir_all(&self.0);
+	}
+}
@@ -0,0 +1,102 @@
+use std::rc::Rc;
+use std::time::Duration;
+
+use anyhow::Result;
+use dioxus::desktop::WindowCloseBehaviour;
+use dioxus::prelude::*;
+use tray_icon::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+#[cfg(target_os = "windows")]
+use tray_icon::Icon;
+use tray_icon::{TrayIcon, TrayIconBuilder};
+
+use crate::{button, config::Config, i18n::tr};
+
+const LAUNCH_ID: &str = "tray_launch";
+const PRELOAD_ID: &str = "tray_preload";
+const SHOW_ID: &str = "tray_show";
+const QUIT_ID: &str = "tray_quit";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+fn build_tray_icon() -> Result<TrayIcon> {
+	let menu = Menu::new();
+	menu.append_items(&[
+		&MenuItem::with_id(LAUNCH_ID, tr("launch_game"), true, None),
+		&MenuItem::with_id(PRELOAD_ID, tr("run_preload_patcher"), true, None),
+		&PredefinedMenuItem::separator(),
+		&MenuItem::with_id(SHOW_ID, tr("tray_show"), true, None),
+		&MenuItem::with_id(QUIT_ID, tr("tray_quit"), true, None),
+	])?;
+	let builder = TrayIconBuilder::new()
+		.with_menu(Box::new(menu))
+		.with_tooltip("MSU Launcher");
+	#[cfg(target_os = "windows")]
+	let builder = builder.with_icon(Icon::from_path(
+		format!("{}/gfx/icons/msu_logo.ico", crate::ASSETS),
+		Some((16, 16)),
+	)?);
+	Ok(builder.build()?)
+}
+
+fn show_window() {
+	let window = dioxus::desktop::window();
+	window.set_visible(true);
+	window.set_minimized(false);
+	window.set_focus();
+}
+
+fn handle_menu_event(event: MenuEvent, config: SyncSignal<Config>) {
+	match event.id.0.as_str() {
+		LAUNCH_ID => {
+			if !config.read().bb_path_known() {
+				tracing::error!("Set the game location before launching Battle Brothers");
+				return;
+			}
+			button::spawn_launch_game(config);
+		}
+		PRELOAD_ID => {
+			if !config.read().bb_path_known() {
+				tracing::error!("Set the game location before running the Preload Patcher");
+				return;
+			}
+			button::spawn_preload_patcher(config.into());
+		}
+		SHOW_ID => show_window(),
+		// closing the last window ends the event loop, which a hidden window doesn't
+		QUIT_ID => dioxus::desktop::window().close(),
+		_ => {}
+	}
+}
+
+#[component]
+pub fn Tray(config: SyncSignal<Config>) -> Element {
+	// the tray icon is removed when dropped, so it has to live as long as the component
+	let tray_icon = use_hook(|| {
+		Rc::new(
+			build_tray_icon()
+				.map_err(|e| tracing::warn!("Couldn't create tray icon: {}", e))
+				.ok(),
+		)
+	});
+	let has_tray_icon = tray_icon.is_some();
+	// a window hidden without a tray icon would leave a process the user can't get back to or quit
+	use_effect(move || {
+		let close_behaviour = if has_tray_icon && config.read().minimize_to_tray() {
+			WindowCloseBehaviour::LastWindowHides
+		} else {
+			WindowCloseBehaviour::LastWindowExitsApp
+		};
+		dioxus::desktop::window().set_close_behavior(close_behaviour);
+	});
+
+	use_future(move || async move {
+		loop {
+			while let Ok(event) = MenuEvent::receiver().try_recv() {
+				handle_menu_event(event, config);
+			}
+			tokio::time::sleep(POLL_INTERVAL).await;
+		}
+	});
+
+	None
+}
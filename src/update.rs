@@ -1,18 +1,30 @@
 use anyhow::{Context, Result};
 use dioxus::prelude::*;
 
-use crate::button::Button;
+use crate::{button::Button, config::Config, i18n::tr, network, patcher_preload::ModInfo};
 
-const API_URL: &str = "https://api.github.com/repos/MSUTeam/MSU-Launcher/releases/latest";
-const RELEASE_URL: &str = "https://www.nexusmods.com/battlebrothers/mods/729?tab=files";
+// forks and redistributions can point the updater at their own releases at build time
+const API_URL: &str = match option_env!("MSU_LAUNCHER_API_URL") {
+	Some(url) => url,
+	None => "https://api.github.com/repos/MSUTeam/MSU-Launcher/releases/latest",
+};
+pub const RELEASE_URL: &str = match option_env!("MSU_LAUNCHER_RELEASE_URL") {
+	Some(url) => url,
+	None => "https://www.nexusmods.com/battlebrothers/mods/729?tab=files",
+};
 
 static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
+const GITHUB_URL: &str = "https://github.com/";
 
-pub async fn check_update_available() -> Result<bool> {
-	let client = reqwest::Client::builder()
+// the GitHub API rejects requests without a user agent
+fn client() -> reqwest::Result<reqwest::Client> {
+	reqwest::Client::builder()
 		.user_agent(APP_USER_AGENT)
 		.build()
-		.context("Couldn't build reqwest agent for update check")?;
+}
+
+pub async fn check_update_available() -> Result<bool> {
+	let client = client().context("Couldn't build reqwest agent for update check")?;
 	let response = client
 		.get(API_URL)
 		.send()
@@ -36,39 +48,185 @@ pub async fn check_update_available() -> Result<bool> {
 			.context("Couldn't parse current version")?)
 }
 
+// releases are usually tagged with a leading v
+fn parse_mod_version(version: &str) -> Result<semver::Version> {
+	let version = version.trim().trim_start_matches('v');
+	semver::Version::parse(version)
+		.with_context(|| format!("Couldn't parse version ({}) as semver", version))
+}
+
+// e.g. https://github.com/owner/repo/ to the API URL of its latest release
+fn latest_release_api_url(repo_url: &str) -> Option<String> {
+	let mut parts = repo_url.strip_prefix(GITHUB_URL)?.split('/');
+	let (owner, repo) = (parts.next()?, parts.next()?);
+	(!owner.is_empty() && !repo.is_empty()).then(|| {
+		format!(
+			"https://api.github.com/repos/{}/{}/releases/latest",
+			owner,
+			repo.trim_end_matches(".git")
+		)
+	})
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ModRelease {
+	pub version: semver::Version,
+	// the release notes, None when the release has none
+	pub changelog: Option<String>,
+	pub url: String,
+}
+
+async fn latest_mod_release(client: &reqwest::Client, repo_url: &str) -> Result<ModRelease> {
+	let api_url = latest_release_api_url(repo_url)
+		.with_context(|| format!("{} isn't a GitHub repository", repo_url))?;
+	let json: serde_json::Value = client
+		.get(&api_url)
+		.send()
+		.await
+		.context("Failed to send update request")?
+		.error_for_status()
+		.context("No release found")?
+		.json()
+		.await
+		.context("Failed to parse update response")?;
+	let tag = json["tag_name"]
+		.as_str()
+		.context("tag_name missing from GitHub API response")?;
+	Ok(ModRelease {
+		version: parse_mod_version(tag)?,
+		changelog: json["body"]
+			.as_str()
+			.map(str::trim)
+			.filter(|body| !body.is_empty())
+			.map(ToOwned::to_owned),
+		url: json["html_url"].as_str().unwrap_or(repo_url).to_owned(),
+	})
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ModUpdate {
+	pub file_name: String,
+	pub release: ModRelease,
+}
+
+// only MSU mods with a semver version and a GitHub source can be checked, the rest are skipped
+pub async fn check_mod_updates(mods: &[ModInfo]) -> Result<Vec<ModUpdate>> {
+	let client = client().context("Couldn't build reqwest agent for mod update check")?;
+	let mut updates = Vec::new();
+	for info in mods {
+		let Some(metadata) = &info.metadata else {
+			continue;
+		};
+		let (Some(version), Some(source)) = (&metadata.version, &metadata.update_source) else {
+			continue;
+		};
+		let installed = match parse_mod_version(version) {
+			Ok(installed) => installed,
+			Err(e) => {
+				tracing::debug!("Not checking {} for updates: {:#}", info.file_name, e);
+				continue;
+			}
+		};
+		match latest_mod_release(&client, source).await {
+			Ok(release) if release.version > installed => updates.push(ModUpdate {
+				file_name: info.file_name.clone(),
+				release,
+			}),
+			Ok(_) => {}
+			Err(e) => tracing::warn!("Couldn't check {} for updates: {:#}", info.file_name, e),
+		}
+	}
+	Ok(updates)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum UpdateStatus {
+	Unknown,
+	Offline,
+	UpToDate,
+	Available,
+}
+
+async fn refresh_update_status(config: SyncSignal<Config>, mut status: Signal<UpdateStatus>) {
+	if !network::refresh_online_status(config).await {
+		status.set(UpdateStatus::Offline);
+		return;
+	}
+	match check_update_available().await {
+		Ok(true) => status.set(UpdateStatus::Available),
+		Ok(false) => status.set(UpdateStatus::UpToDate),
+		Err(e) => {
+			status.set(UpdateStatus::Unknown);
+			tracing::error!("Failed to check for updates: {:?}", e)
+		}
+	}
+}
+
 #[component]
 pub fn UpdateButton(
 	#[props(default = "".to_string())] class: String,
 	style: Option<String>,
+	config: SyncSignal<Config>,
 ) -> Element {
-	let mut disabled = use_signal(|| true);
-	use_future(move || async move {
-		match check_update_available().await {
-			Ok(update_available) => disabled.set(!update_available),
-			Err(e) => tracing::error!("Failed to check for updates: {:?}", e),
-		}
+	let status = use_signal(|| UpdateStatus::Unknown);
+	use_future(move || refresh_update_status(config, status));
+	let disabled = use_memo(move || {
+		matches!(
+			*status.read(),
+			UpdateStatus::Unknown | UpdateStatus::UpToDate
+		)
 	});
 	rsx!(
 		div { class: "{class} flex flex-row items-center", style,
-			if !*disabled.read() {
+			if *status.read() == UpdateStatus::Available {
 				img { class: "w-10 h-10", src: "assets/gfx/icons/warning.svg" }
 			}
 			Button {
-				onclick: |_| {
+				onclick: move |_| {
+					if *status.read() == UpdateStatus::Offline {
+						spawn(refresh_update_status(config, status));
+						return;
+					}
 					match open::that(RELEASE_URL) {
-						Ok(_) => tracing::info!("Opened NexusMods"),
-						Err(e) => tracing::error!("Failed to open NexusMods: {}", e),
+						Ok(_) => tracing::info!("Opened release page"),
+						Err(e) => tracing::error!("Failed to open release page: {}", e),
 					}
 				},
 				disabled,
 				{
-					if *disabled.read() {
-						"No Update Available"
-					} else {
-						"Update Available, Download Here!"
+					match *status.read() {
+						UpdateStatus::Unknown | UpdateStatus::UpToDate => tr("update_none"),
+						UpdateStatus::Offline => tr("update_offline"),
+						UpdateStatus::Available => tr("update_available"),
 					}
 				}
 			}
 		}
 	)
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn mod_release_urls() {
+		assert_eq!(
+			latest_release_api_url("https://github.com/MSUTeam/MSU/").as_deref(),
+			Some("https://api.github.com/repos/MSUTeam/MSU/releases/latest")
+		);
+		assert_eq!(
+			latest_release_api_url("https://github.com/MSUTeam/MSU.git/tree/main").as_deref(),
+			Some("https://api.github.com/repos/MSUTeam/MSU/releases/latest")
+		);
+		assert!(latest_release_api_url("https://github.com/MSUTeam").is_none());
+		assert!(
+			latest_release_api_url("https://www.nexusmods.com/battlebrothers/mods/479").is_none()
+		);
+		assert_eq!(
+			parse_mod_version("v1.2.0").unwrap(),
+			semver::Version::new(1, 2, 0)
+		);
+		assert!(parse_mod_version("1.5").is_err());
+	}
+}
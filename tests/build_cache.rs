@@ -0,0 +1,6 @@
+// build scripts aren't compiled by `cargo test`, so the build cache's tests run through this target
+#[allow(dead_code)]
+#[path = "../build/cache.rs"]
+mod cache;
+#[path = "../src/temp_dir.rs"]
+mod temp_dir;